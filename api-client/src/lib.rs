@@ -1,9 +1,12 @@
+use std::collections::BTreeMap;
 use std::time::Duration;
 
 use anyhow::{Context, Result};
+use bytes::Bytes;
+use chrono::{DateTime, SecondsFormat, Utc};
 use headers::{Authorization, HeaderMapExt};
 use percent_encoding::utf8_percent_encode;
-use reqwest::header::HeaderMap;
+use reqwest::header::{self, HeaderMap};
 use reqwest::Response;
 use reqwest_middleware::{ClientWithMiddleware, RequestBuilder};
 use serde::{Deserialize, Serialize};
@@ -15,12 +18,15 @@ use cyndra_common::models::{
     deployment::{
         DeploymentListResponse, DeploymentRequest, DeploymentResponse, UploadArchiveResponse,
     },
+    error::ApiError,
     log::LogsResponse,
     project::{ProjectCreateRequest, ProjectListResponse, ProjectResponse, ProjectUpdateRequest},
     resource::{ProvisionResourceRequest, ResourceListResponse, ResourceResponse, ResourceType},
-    team::TeamListResponse,
+    secret::{SecretKeysResponse, SecretsSetRequest, SecretsUnsetRequest},
+    team::{AddTeamMemberRequest, TeamListResponse, TeamMembersResponse},
     user::UserResponse,
 };
+use tokio::io::{AsyncRead, AsyncReadExt};
 use tokio::net::TcpStream;
 use tokio_tungstenite::tungstenite::client::IntoClientRequest;
 use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
@@ -35,6 +41,9 @@ use tracing::{debug, error};
 pub mod util;
 use util::ToBodyContent;
 
+/// Archives are uploaded in chunks of this size, which is how often upload progress is reported
+const UPLOAD_CHUNK_SIZE: usize = 64 * 1024;
+
 #[derive(Clone)]
 pub struct CyndraApiClient {
     pub client: ClientWithMiddleware,
@@ -110,19 +119,63 @@ impl CyndraApiClient {
         self.post_json(path, Some(deployment_req)).await
     }
 
+    /// Find an archive that was already uploaded to a project by its SHA-256 hash
+    pub async fn get_archive_by_hash(
+        &self,
+        project: &str,
+        sha256: &str,
+    ) -> Result<Option<UploadArchiveResponse>> {
+        let path = format!("/projects/{project}/archives?sha256={sha256}");
+
+        match self.get_json(path).await {
+            Ok(archive) => Ok(archive),
+            // no archive with this hash was uploaded
+            Err(e)
+                if e.downcast_ref::<ApiError>()
+                    .is_some_and(|e| e.status().ok() == Some(http::StatusCode::NOT_FOUND)) =>
+            {
+                Ok(None)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Uploads an archive of `len` bytes from `archive`, calling `progress` with the number of bytes
+    /// sent so far. The archive is read in chunks as they are sent, so it is never fully in memory.
     pub async fn upload_archive(
         &self,
         project: &str,
-        data: Vec<u8>,
+        archive: impl AsyncRead + Send + Unpin + 'static,
+        len: u64,
+        sha256: &str,
+        progress: impl Fn(u64) + Send + Sync + 'static,
     ) -> Result<UploadArchiveResponse> {
-        let path = format!("/projects/{project}/archives");
+        let path = format!("/projects/{project}/archives?sha256={sha256}");
 
         let url = format!("{}{}", self.api_url, path);
         let mut builder = self.client.post(url);
         builder = self.set_auth_bearer(builder);
 
+        // the next chunk is only read when reqwest is ready to send it
+        let chunks = futures::stream::try_unfold(
+            (archive, 0, progress),
+            |(mut archive, sent, progress)| async move {
+                let mut chunk = vec![0; UPLOAD_CHUNK_SIZE];
+                let read = archive.read(&mut chunk).await?;
+                if read == 0 {
+                    return Ok::<_, std::io::Error>(None);
+                }
+                chunk.truncate(read);
+                let sent = sent + read as u64;
+                progress(sent);
+
+                Ok(Some((Bytes::from(chunk), (archive, sent, progress))))
+            },
+        );
+
         builder
-            .body(data)
+            .header(header::CONTENT_LENGTH, len)
+            .body(reqwest::Body::wrap_stream(chunks))
             .send()
             .await
             .context("failed to upload archive")?
@@ -195,6 +248,29 @@ impl CyndraApiClient {
             .await
     }
 
+    pub async fn set_secrets(
+        &self,
+        project: &str,
+        secrets: BTreeMap<String, String>,
+    ) -> Result<SecretKeysResponse> {
+        self.put_json(
+            format!("/projects/{project}/secrets"),
+            Some(SecretsSetRequest { secrets }),
+        )
+        .await
+    }
+    pub async fn unset_secrets(
+        &self,
+        project: &str,
+        keys: Vec<String>,
+    ) -> Result<SecretKeysResponse> {
+        self.delete_json_with_body(
+            format!("/projects/{project}/secrets"),
+            SecretsUnsetRequest { keys },
+        )
+        .await
+    }
+
     pub async fn list_certificates(&self, project: &str) -> Result<CertificateListResponse> {
         self.get_json(format!("/projects/{project}/certificates"))
             .await
@@ -249,11 +325,28 @@ impl CyndraApiClient {
         self.delete_json(format!("/projects/{project}")).await
     }
 
-    #[allow(unused)]
-    async fn get_teams_list(&self) -> Result<TeamListResponse> {
+    pub async fn get_teams_list(&self) -> Result<TeamListResponse> {
         self.get_json("/teams").await
     }
 
+    pub async fn get_team_members(&self, team_id: &str) -> Result<TeamMembersResponse> {
+        self.get_json(format!("/teams/{team_id}/members")).await
+    }
+
+    pub async fn add_team_member(
+        &self,
+        team_id: &str,
+        req: AddTeamMemberRequest,
+    ) -> Result<String> {
+        self.post_json(format!("/teams/{team_id}/members"), Some(req))
+            .await
+    }
+
+    pub async fn remove_team_member(&self, team_id: &str, user_id: &str) -> Result<String> {
+        self.delete_json(format!("/teams/{team_id}/members/{user_id}"))
+            .await
+    }
+
     pub async fn get_deployment_logs(
         &self,
         project: &str,
@@ -269,6 +362,29 @@ impl CyndraApiClient {
         self.get_json(path).await
     }
 
+    /// Stream new log lines of a deployment, one JSON [`LogItem`](cyndra_common::models::log::LogItem) per text message.
+    /// If `since` is set, lines after that time are sent first.
+    pub async fn get_deployment_logs_ws(
+        &self,
+        project: &str,
+        deployment_id: &str,
+        since: Option<DateTime<Utc>>,
+    ) -> Result<WebSocketStream<MaybeTlsStream<TcpStream>>> {
+        let path = format!("/projects/{project}/deployments/{deployment_id}/logs/ws");
+
+        self.ws_get(with_since_query(path, since)).await
+    }
+    /// Like [`Self::get_deployment_logs_ws`], but for all deployments in the project
+    pub async fn get_project_logs_ws(
+        &self,
+        project: &str,
+        since: Option<DateTime<Utc>>,
+    ) -> Result<WebSocketStream<MaybeTlsStream<TcpStream>>> {
+        let path = format!("/projects/{project}/logs/ws");
+
+        self.ws_get(with_since_query(path, since)).await
+    }
+
     pub async fn get_deployments(
         &self,
         project: &str,
@@ -480,3 +596,107 @@ impl CyndraApiClient {
         self.delete(path, Some(body)).await?.to_json().await
     }
 }
+
+fn with_since_query(path: String, since: Option<DateTime<Utc>>) -> String {
+    match since {
+        Some(since) => {
+            let since = since.to_rfc3339_opts(SecondsFormat::Micros, true);
+            let since = utf8_percent_encode(&since, percent_encoding::NON_ALPHANUMERIC);
+            format!("{path}?since={since}")
+        }
+        None => path,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    /// Serve one request with `status` and an API error body
+    async fn serve_error(status: http::StatusCode) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buf = [0; 1024];
+            let _ = stream.read(&mut buf).await.unwrap();
+            let body = serde_json::to_string(&ApiError::new("oops", status)).unwrap();
+            let response = format!(
+                "HTTP/1.1 {status}\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                body.len()
+            );
+            stream.write_all(response.as_bytes()).await.unwrap();
+        });
+
+        format!("http://{addr}")
+    }
+
+    #[tokio::test]
+    async fn get_archive_by_hash_handles_not_found() {
+        let url = serve_error(http::StatusCode::NOT_FOUND).await;
+        let client = CyndraApiClient::new(url, None, None, None);
+        assert!(client
+            .get_archive_by_hash("proj_1", "abc")
+            .await
+            .unwrap()
+            .is_none());
+
+        let url = serve_error(http::StatusCode::INTERNAL_SERVER_ERROR).await;
+        let client = CyndraApiClient::new(url, None, None, None);
+        assert!(client.get_archive_by_hash("proj_1", "abc").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn upload_archive_streams_body() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let received = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0; 64 * 1024];
+            // read until the whole body of the request is there
+            let body = loop {
+                let read = stream.read(&mut buf).await.unwrap();
+                request.extend_from_slice(&buf[..read]);
+                let text = String::from_utf8_lossy(&request);
+                if let Some((_, body)) = text.split_once("\r\n\r\n") {
+                    if body.len() == 100_000 {
+                        break body.to_owned();
+                    }
+                }
+            };
+            let body_json = r#"{"archive_version_id":"v1"}"#;
+            let response = format!(
+                "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body_json}",
+                body_json.len()
+            );
+            stream.write_all(response.as_bytes()).await.unwrap();
+
+            body
+        });
+
+        let archive = "a".repeat(100_000);
+        let sent = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let progress = sent.clone();
+        let client = CyndraApiClient::new(url, None, None, None);
+        let response = client
+            .upload_archive(
+                "proj_1",
+                std::io::Cursor::new(archive.clone().into_bytes()),
+                archive.len() as u64,
+                "abc",
+                move |bytes| progress.lock().unwrap().push(bytes),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.archive_version_id, "v1");
+        assert_eq!(received.await.unwrap(), archive);
+        assert_eq!(*sent.lock().unwrap(), [UPLOAD_CHUNK_SIZE as u64, 100_000]);
+    }
+}
@@ -7,12 +7,20 @@ use std::{
 
 use anyhow::{bail, Context};
 use cargo_metadata::MetadataCommand;
+use chrono::{DateTime, Utc};
 use clap::{
-    builder::{OsStringValueParser, PossibleValue, TypedValueParser},
+    builder::{OsStringValueParser, PossibleValue, PossibleValuesParser, TypedValueParser},
     Args, Parser, Subcommand, ValueEnum,
 };
 use clap_complete::Shell;
-use cyndra_common::{constants::EXAMPLES_REPO, models::resource::ResourceType};
+use regex::Regex;
+use cyndra_common::{
+    constants::EXAMPLES_REPO,
+    models::{resource::ResourceType, team::TeamRole},
+};
+use tracing::Level;
+
+use crate::util::logs::parse_timestamp;
 
 #[derive(Parser)]
 #[command(
@@ -42,6 +50,15 @@ pub struct CyndraArgs {
     /// Turn on tracing output for Cyndra libraries. (WARNING: can print sensitive data)
     #[arg(global = true, long, env = "cyndra_DEBUG")]
     pub debug: bool,
+    /// Format to print command output and errors in
+    #[arg(
+        global = true,
+        long = "output",
+        env = "cyndra_OUTPUT_MODE",
+        value_enum,
+        default_value_t = OutputMode::Normal
+    )]
+    pub output_mode: OutputMode,
     #[command(flatten)]
     pub project_args: ProjectArgs,
 
@@ -49,6 +66,15 @@ pub struct CyndraArgs {
     pub cmd: Command,
 }
 
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputMode {
+    /// Human readable text, tables and colors
+    #[default]
+    Normal,
+    /// The API response models as JSON
+    Json,
+}
+
 /// Global args for subcommands that deal with projects
 #[derive(Args, Clone, Debug)]
 pub struct ProjectArgs {
@@ -124,6 +150,12 @@ pub enum Command {
     /// Manage SSL certificates for custom domains
     #[command(subcommand, visible_alias = "cert")]
     Certificate(CertificateCommand),
+    /// Manage the secrets of a project
+    #[command(subcommand)]
+    Secrets(SecretsCommand),
+    /// Manage teams and their members
+    #[command(subcommand)]
+    Team(TeamCommand),
     /// Show info about your Cyndra account
     #[command(visible_alias = "acc")]
     Account,
@@ -151,7 +183,7 @@ pub enum GenerateCommand {
         /// The shell to generate shell completion for
         shell: Shell,
         /// Output to a file (stdout by default)
-        #[arg(short, long)]
+        #[arg(short, long = "output-file")]
         output: Option<PathBuf>,
     },
     /// Generate man page to the standard output
@@ -196,6 +228,24 @@ pub enum DeploymentCommand {
         #[command(flatten)]
         tracking_args: DeploymentTrackingArgs,
     },
+    /// Show the commits and build changes between two deployments
+    Diff {
+        /// ID of the older deployment
+        from: String,
+        /// ID of the newer deployment
+        to: String,
+    },
+    /// Redeploy an earlier successful deployment
+    Rollback {
+        /// How many successful deployments to go back
+        #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u32).range(1..))]
+        steps: u32,
+
+        #[command(flatten)]
+        confirmation: ConfirmationArgs,
+        #[command(flatten)]
+        tracking_args: DeploymentTrackingArgs,
+    },
     /// Stop running deployment(s)
     Stop {
         #[command(flatten)]
@@ -225,13 +275,72 @@ pub enum ResourceCommand {
         #[command(flatten)]
         confirmation: ConfirmationArgs,
     },
-    /// Dump a resource
-    #[command(hide = true)] // not yet supported on cyndra.dev
+    /// Remove the local containers and volumes that `cyndra run` created for this project
+    Reset {
+        #[command(flatten)]
+        confirmation: ConfirmationArgs,
+    },
+    /// Write an SQL dump of a database resource
     Dump {
         /// Type of the resource to dump.
         /// Use the string in the 'Type' column as displayed in the `resource list` command.
         /// For example, 'database::shared::postgres'.
         resource_type: ResourceType,
+        /// Dump the database of `cyndra run` instead of the deployed one
+        #[arg(long)]
+        local: bool,
+        /// Name of the local database, if the resource sets one with `database_name`
+        #[arg(long, requires = "local")]
+        db_name: Option<String>,
+        /// File to write the dump to (stdout by default)
+        #[arg(short, long = "output-file")]
+        output: Option<PathBuf>,
+    },
+    /// Restore an SQL dump into the database of `cyndra run`
+    Restore {
+        /// Type of the resource to restore.
+        /// Use the string in the 'Type' column as displayed in the `resource list` command.
+        /// For example, 'database::shared::postgres'.
+        resource_type: ResourceType,
+        /// File with the SQL dump, for example from `resource dump`
+        input: PathBuf,
+        /// Name of the local database, if the resource sets one with `database_name`
+        #[arg(long)]
+        db_name: Option<String>,
+        #[command(flatten)]
+        confirmation: ConfirmationArgs,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum SecretsCommand {
+    /// List the secrets of the project (values are hidden)
+    #[command(visible_alias = "ls")]
+    List {
+        #[command(flatten)]
+        table: TableArgs,
+    },
+    /// Add or change secrets without making a new deployment
+    Set {
+        /// Secrets to set, as KEY or KEY=VALUE.
+        /// The value of a bare KEY is read from --from-file, stdin, or a prompt.
+        /// Values given as KEY=VALUE end up in the shell history and the process list.
+        #[arg(required = true, value_parser = parse_secret)]
+        secrets: Vec<(String, Option<String>)>,
+        /// Read the value of the single bare KEY from this file
+        #[arg(long, value_name = "PATH")]
+        from_file: Option<PathBuf>,
+    },
+    /// Remove secrets
+    Unset {
+        /// Keys of the secrets to remove
+        #[arg(required = true)]
+        keys: Vec<String>,
+    },
+    /// Compare local secrets with the project's secrets
+    Diff {
+        #[command(flatten)]
+        secret_args: SecretsArgs,
     },
 }
 
@@ -280,6 +389,64 @@ pub enum ProjectCommand {
     Delete(ConfirmationArgs),
     /// Link this workspace to a Cyndra project
     Link,
+    /// Move the project to a team, or back to your personal projects
+    Transfer {
+        /// ID or name of the team to move the project to
+        #[arg(long, required_unless_present = "personal")]
+        team: Option<String>,
+        /// Move the project out of its team
+        #[arg(long, conflicts_with = "team")]
+        personal: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum TeamCommand {
+    /// List the teams you are a member of
+    #[command(visible_alias = "ls")]
+    List {
+        #[command(flatten)]
+        table: TableArgs,
+    },
+    /// List the members and pending invites of a team
+    Members {
+        #[command(flatten)]
+        team_args: TeamArgs,
+        #[command(flatten)]
+        table: TableArgs,
+    },
+    /// Invite a user to a team by email
+    Invite {
+        /// Email address to send the invite to
+        email: String,
+        /// Role of the user in the team
+        #[arg(
+            long,
+            default_value = "member",
+            value_parser = PossibleValuesParser::new(["owner", "admin", "member"])
+                .map(|role| role.parse::<TeamRole>().expect("a known team role")),
+        )]
+        role: TeamRole,
+        #[command(flatten)]
+        team_args: TeamArgs,
+    },
+    /// Remove a member from a team
+    #[command(visible_alias = "rm")]
+    Remove {
+        /// User ID or email of the member to remove
+        user: String,
+        #[command(flatten)]
+        team_args: TeamArgs,
+        #[command(flatten)]
+        confirmation: ConfirmationArgs,
+    },
+}
+
+#[derive(Args, Debug)]
+pub struct TeamArgs {
+    /// ID or name of the team (can be left out if you are in only one team)
+    #[arg(long)]
+    pub team: Option<String>,
 }
 
 #[derive(Subcommand, Debug)]
@@ -318,9 +485,34 @@ pub struct LogoutArgs {
 
 #[derive(Args, Default)]
 pub struct DeployArgs {
-    /// WIP: Deploy this Docker image instead of building one
-    #[arg(long, short = 'i', hide = true)]
+    /// Deploy this Docker image instead of building one
+    #[arg(long, short = 'i')]
     pub image: Option<String>,
+    /// Build the image locally with Docker and push it to the registry instead of building remotely
+    #[arg(long, conflicts_with_all = ["image", "git", "output_archive"])]
+    pub local_build: bool,
+    /// Registry to push locally built images to, such as `ghcr.io/my-org`.
+    /// Overrides `registry` in the build section of Cyndra.toml.
+    #[arg(long, env = "cyndra_REGISTRY")]
+    pub registry: Option<String>,
+
+    /// Deploy from this git repository instead of uploading the local files.
+    /// Build options such as the package, binary and features are read
+    /// from the Cargo.toml and Cyndra.toml at the ref when it is built.
+    #[arg(
+        long,
+        value_name = "URL",
+        requires = "git_ref",
+        conflicts_with_all = ["image", "output_archive"]
+    )]
+    pub git: Option<String>,
+    /// Branch, tag or commit to deploy from the git repository.
+    /// It is resolved to a commit in the local repository, so fetch it first.
+    #[arg(long = "ref", value_name = "REF", requires = "git")]
+    pub git_ref: Option<String>,
+    /// Path of the project inside the git repository, if not at its root
+    #[arg(long, value_name = "PATH", requires = "git")]
+    pub subdirectory: Option<String>,
 
     /// Allow deployment with uncommitted files
     #[arg(long, visible_alias = "ad")]
@@ -328,13 +520,77 @@ pub struct DeployArgs {
     /// Output the deployment archive to a file instead of sending a deployment request
     #[arg(long)]
     pub output_archive: Option<PathBuf>,
+    /// List the files that would be in the deployment archive and why, without deploying
+    #[arg(long, conflicts_with_all = ["image", "local_build", "git", "output_archive"])]
+    pub dry_run: bool,
+    /// Package to deploy (required if the workspace has multiple Cyndra packages)
+    #[arg(long, conflicts_with = "git")]
+    pub package: Option<String>,
+    /// Binary target to deploy (required if the package has multiple binaries)
+    #[arg(long, conflicts_with = "git")]
+    pub bin: Option<String>,
 
     #[command(flatten)]
     pub tracking_args: DeploymentTrackingArgs,
 
+    #[command(flatten)]
+    pub health_args: HealthCheckArgs,
+
     #[command(flatten)]
     pub secret_args: SecretsArgs,
 }
+
+#[derive(Args)]
+#[command(next_help_heading = "Health check options")]
+pub struct HealthCheckArgs {
+    /// After the deployment is running, probe its URIs until they respond with the expected status
+    #[arg(long, conflicts_with = "no_follow")]
+    pub wait_healthy: bool,
+    /// Path to probe on each URI
+    #[arg(
+        long,
+        value_name = "PATH",
+        default_value = "/",
+        requires = "wait_healthy"
+    )]
+    pub smoke: String,
+    /// Status code that the probes should respond with
+    #[arg(
+        long,
+        default_value_t = 200,
+        value_parser = clap::value_parser!(u16).range(100..600),
+        requires = "wait_healthy"
+    )]
+    pub smoke_status: u16,
+    /// Seconds to wait for the probes to succeed
+    #[arg(
+        long,
+        value_name = "SECONDS",
+        default_value_t = 120,
+        requires = "wait_healthy"
+    )]
+    pub smoke_timeout: u64,
+    /// Pass the health checks when the deployment has no URIs to probe, instead of failing
+    #[arg(long, requires = "wait_healthy")]
+    pub allow_no_uris: bool,
+    /// Redeploy the previously running deployment if the deployment or the probes fail
+    #[arg(long, requires = "wait_healthy")]
+    pub rollback_on_failure: bool,
+}
+
+impl Default for HealthCheckArgs {
+    fn default() -> Self {
+        Self {
+            wait_healthy: false,
+            smoke: "/".to_owned(),
+            smoke_status: 200,
+            smoke_timeout: 120,
+            allow_no_uris: false,
+            rollback_on_failure: false,
+        }
+    }
+}
+
 #[derive(Args, Default)]
 pub struct DeploymentTrackingArgs {
     /// Don't follow the deployment status, exit after the operation begins
@@ -362,6 +618,9 @@ pub struct RunArgs {
     /// Uses bacon crate to run the project in watch mode
     #[arg(long)]
     pub bacon: bool,
+    /// Only run this package in the workspace (default: all Cyndra packages)
+    #[arg(long)]
+    pub package: Option<String>,
 
     #[command(flatten)]
     pub secret_args: SecretsArgs,
@@ -492,23 +751,40 @@ pub struct LogsArgs {
     #[arg(short, long)]
     /// View logs from the most recent deployment (which is not always the running one)
     pub latest: bool,
-    #[arg(short, long, hide = true)]
-    /// Follow log output
+    #[arg(short, long)]
+    /// Follow log output, reconnecting if the connection drops
     pub follow: bool,
     /// Don't display timestamps and log origin tags
     #[arg(long)]
     pub raw: bool,
     /// View the first N log lines
-    #[arg(long, group = "output_mode", hide = true)]
+    #[arg(long, group = "log_range", conflicts_with = "follow")]
     pub head: Option<u32>,
     /// View the last N log lines
-    #[arg(long, group = "output_mode", hide = true)]
+    #[arg(long, group = "log_range")]
     pub tail: Option<u32>,
     /// View all log lines
-    #[arg(long, group = "output_mode", hide = true)]
+    #[arg(long, group = "log_range")]
     pub all: bool,
+    /// Only view log lines after this time.
+    /// Either a timestamp like 2024-05-01T12:00:00Z or a duration ago like 30s, 15m, 2h or 1d
+    #[arg(long, value_parser = parse_timestamp)]
+    pub since: Option<DateTime<Utc>>,
+    /// Only view log lines before this time. Same format as `--since`
+    #[arg(long, value_parser = parse_timestamp, conflicts_with = "follow")]
+    pub until: Option<DateTime<Utc>>,
+    /// Only view log lines from these sources, such as build or app
+    #[arg(long, value_delimiter = ',')]
+    pub source: Vec<String>,
+    /// Only view log lines matching this regex
+    #[arg(long)]
+    pub grep: Option<Regex>,
+    /// Only view log lines at this level or more severe (error, warn, info, debug, trace).
+    /// Works for lines printed by a tracing subscriber, both as text and as JSON
+    #[arg(long)]
+    pub level: Option<Level>,
     /// Get logs from all deployments instead of one deployment
-    #[arg(long, hide = true)]
+    #[arg(long)]
     pub all_deployments: bool,
 }
 
@@ -522,6 +798,14 @@ fn parse_path(path: OsString) -> Result<PathBuf, io::Error> {
     })
 }
 
+fn parse_secret(s: &str) -> Result<(String, Option<String>), String> {
+    match s.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_owned(), Some(value.to_owned()))),
+        None if !s.is_empty() => Ok((s.to_owned(), None)),
+        _ => Err(format!("expected KEY or KEY=VALUE, got '{s}'")),
+    }
+}
+
 /// Helper function to parse, create if not exists, and return the absolute path
 pub(crate) fn create_and_parse_path(path: OsString) -> Result<PathBuf, io::Error> {
     // Create the directory if does not exist
@@ -546,6 +830,71 @@ mod tests {
         CyndraArgs::command().debug_assert();
     }
 
+    #[test]
+    fn output_mode_is_global() {
+        for args in [
+            &["cyndra", "--output", "json", "deployment", "list"][..],
+            &["cyndra", "deployment", "list", "--output", "json"],
+            &["cyndra", "logs", "--output", "json"],
+        ] {
+            let args = CyndraArgs::try_parse_from(args).unwrap();
+            assert_eq!(args.output_mode, OutputMode::Json);
+        }
+
+        let args = CyndraArgs::try_parse_from([
+            "cyndra",
+            "resource",
+            "dump",
+            "database::shared::postgres",
+            "--output",
+            "json",
+            "-o",
+            "dump.sql",
+        ])
+        .unwrap();
+        assert_eq!(args.output_mode, OutputMode::Json);
+        let Command::Resource(ResourceCommand::Dump { output, .. }) = args.cmd else {
+            panic!("expected resource dump");
+        };
+        assert_eq!(output, Some(PathBuf::from("dump.sql")));
+    }
+
+    #[test]
+    fn parses_team_roles() {
+        let role = |args: &[&str]| {
+            let args = ["cyndra", "team", "invite", "me@example.com"]
+                .iter()
+                .chain(args);
+            match CyndraArgs::try_parse_from(args).map(|a| a.cmd) {
+                Ok(Command::Team(TeamCommand::Invite { role, .. })) => Ok(role),
+                Ok(_) => panic!("expected team invite"),
+                Err(e) => Err(e.kind()),
+            }
+        };
+
+        assert_eq!(role(&[]), Ok(TeamRole::Member));
+        assert_eq!(role(&["--role", "admin"]), Ok(TeamRole::Admin));
+        assert_eq!(
+            role(&["--role", "admn"]),
+            Err(clap::error::ErrorKind::InvalidValue)
+        );
+    }
+
+    #[test]
+    fn parses_secrets() {
+        assert_eq!(
+            parse_secret("KEY=a=b"),
+            Ok(("KEY".to_owned(), Some("a=b".to_owned())))
+        );
+        assert_eq!(
+            parse_secret("KEY="),
+            Ok(("KEY".to_owned(), Some(String::new())))
+        );
+        assert_eq!(parse_secret("KEY"), Ok(("KEY".to_owned(), None)));
+        assert!(parse_secret("=value").is_err());
+        assert!(parse_secret("").is_err());
+    }
+
     #[test]
     fn test_init_args_framework() {
         // pre-defined template (only hello world)
@@ -1,13 +1,17 @@
-use anyhow::Result;
-use cargo_cyndra::{parse_args, setup_tracing, Binary, Cyndra};
+use cargo_cyndra::{exit_with_error, parse_args, setup_tracing, Binary, Cyndra};
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() {
     let (args, provided_path_to_init) = parse_args();
 
     setup_tracing(args.debug);
 
-    Cyndra::new(Binary::CargoCyndra, args.api_env.clone())?
-        .run(args, provided_path_to_init)
-        .await
+    let output_mode = args.output_mode;
+    let res = match Cyndra::new(Binary::CargoCyndra, args.api_env.clone()) {
+        Ok(cyndra) => cyndra.run(args, provided_path_to_init).await,
+        Err(e) => Err(e),
+    };
+    if let Err(e) = res {
+        exit_with_error(e, output_mode);
+    }
 }
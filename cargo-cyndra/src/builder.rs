@@ -4,7 +4,7 @@ use std::process::Stdio;
 
 use anyhow::{anyhow, bail, Context};
 use cargo_metadata::{Metadata, Package};
-use cyndra_common::constants::RUNTIME_NAME;
+use cyndra_common::{constants::RUNTIME_NAME, models::deployment::BuildArgsRust};
 use tokio::io::AsyncBufReadExt;
 use tracing::{debug, error, info, trace};
 
@@ -59,12 +59,14 @@ fn extract_cyndra_toml_name(path: PathBuf) -> anyhow::Result<String> {
     Ok(name)
 }
 
-/// Given a project directory path, builds the crate
+/// Given a project directory path, builds the crate.
+/// If `package` is given, only that package is built instead of all Cyndra packages in the workspace.
 pub async fn build_workspace(
     project_path: &Path,
     release_mode: bool,
     tx: tokio::sync::mpsc::Sender<String>,
     deployment: bool,
+    package: Option<&str>,
 ) -> anyhow::Result<Vec<BuiltService>> {
     let project_path = project_path.to_owned();
     let manifest_path = project_path.join("Cargo.toml");
@@ -100,13 +102,24 @@ pub async fn build_workspace(
     notification.abort();
 
     let metadata = async_cargo_metadata(manifest_path.as_path()).await?;
-    let packages = find_cyndra_packages(&metadata)?;
+    let mut packages = find_cyndra_packages(&metadata)?;
     if packages.is_empty() {
         bail!(
             "Did not find any packages that Cyndra can run. \
             Make sure your crate has a binary target that uses `#[cyndra_runtime::main]`."
         );
     }
+    if let Some(package) = package {
+        let names = packages
+            .iter()
+            .map(|p| p.name.as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
+        packages.retain(|p| p.name == package);
+        if packages.is_empty() {
+            bail!("Package '{package}' is not a Cyndra package in this workspace. Found: {names}");
+        }
+    }
 
     let services = compile(
         packages,
@@ -177,6 +190,69 @@ pub fn find_cyndra_packages(metadata: &Metadata) -> anyhow::Result<Vec<Package>>
     Ok(packages)
 }
 
+/// Pick the package to deploy out of the Cyndra packages in a workspace.
+/// A name is required if there is more than one package to choose from.
+pub fn select_package<'a>(
+    packages: &'a [Package],
+    name: Option<&str>,
+) -> anyhow::Result<&'a Package> {
+    let names = packages
+        .iter()
+        .map(|p| p.name.as_str())
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    match name {
+        Some(name) => packages.iter().find(|p| p.name == name).with_context(|| {
+            format!("Package '{name}' is not a Cyndra package in this workspace. Found: {names}")
+        }),
+        None => match packages {
+            [] => bail!(
+                "Did not find any packages that Cyndra can run. \
+                Make sure your crate has a binary target that uses `#[cyndra_runtime::main]`."
+            ),
+            [package] => Ok(package),
+            _ => bail!(
+                "Found multiple Cyndra packages in this workspace: {names}. \
+                Pick one with `--package` or the `build.package` key in Cyndra.toml."
+            ),
+        },
+    }
+}
+
+/// Pick the binary target to deploy in a package.
+/// A name is required if the package has more than one binary target.
+pub fn select_binary(package: &Package, name: Option<&str>) -> anyhow::Result<String> {
+    let bins = package
+        .targets
+        .iter()
+        .filter(|t| t.is_bin())
+        .map(|t| t.name.as_str())
+        .collect::<Vec<_>>();
+
+    match name {
+        Some(name) => {
+            if !bins.contains(&name) {
+                bail!(
+                    "Package '{}' does not have a binary target named '{name}'. Found: {}",
+                    package.name,
+                    bins.join(", ")
+                );
+            }
+            Ok(name.to_owned())
+        }
+        None => match bins.as_slice() {
+            [bin] => Ok(bin.to_string()),
+            _ => bail!(
+                "Package '{}' has multiple binary targets: {}. \
+                Pick one with `--bin` or the `build.binary` key in Cyndra.toml.",
+                package.name,
+                bins.join(", ")
+            ),
+        },
+    }
+}
+
 // Only used in deployer
 pub async fn clean_crate(project_path: &Path) -> anyhow::Result<()> {
     let manifest_path = project_path.join("Cargo.toml");
@@ -240,23 +316,7 @@ async fn compile(
         "debug"
     };
 
-    cmd.stderr(Stdio::piped());
-    cmd.stdout(Stdio::null());
-    let mut handle = cmd.spawn()?;
-    let reader = tokio::io::BufReader::new(handle.stderr.take().unwrap());
-    tokio::spawn(async move {
-        let mut lines = reader.lines();
-        while let Some(line) = lines.next_line().await.unwrap() {
-            let _ = tx
-                .send(line)
-                .await
-                .map_err(|error| error!(error = &error as &dyn std::error::Error));
-        }
-    });
-    let status = handle.wait().await?;
-    if !status.success() {
-        bail!("Build failed. Is the Cyndra runtime missing?");
-    }
+    run_cargo_build(cmd, tx).await?;
 
     let services = packages
         .iter()
@@ -282,3 +342,122 @@ async fn compile(
 
     Ok(services)
 }
+
+/// Build the package and binary selected in `build_args` in release mode, with the same features
+/// that a deployment is built with. Returns the path to the executable.
+pub async fn build_binary(
+    project_path: &Path,
+    build_args: &BuildArgsRust,
+    tx: tokio::sync::mpsc::Sender<String>,
+) -> anyhow::Result<PathBuf> {
+    let manifest_path = project_path.join("Cargo.toml");
+    if !manifest_path.exists() {
+        bail!("Cargo manifest file not found: {}", manifest_path.display());
+    }
+    let (Some(package), Some(binary)) = (&build_args.package_name, &build_args.binary_name) else {
+        bail!("a package and binary to build are needed");
+    };
+    let metadata = async_cargo_metadata(manifest_path.as_path()).await?;
+
+    let mut cmd = tokio::process::Command::new("cargo");
+    cmd.arg("build")
+        .arg("--manifest-path")
+        .arg(&manifest_path)
+        .arg("--color=always") // piping disables auto color, but we want it
+        .arg("--release")
+        .arg("--package")
+        .arg(package)
+        .arg("--bin")
+        .arg(binary)
+        .current_dir(project_path);
+    if build_args.no_default_features {
+        cmd.arg("--no-default-features");
+    }
+    if let Some(ref features) = build_args.features {
+        cmd.arg("--features").arg(features);
+    }
+
+    run_cargo_build(cmd, tx).await?;
+
+    let mut path = metadata
+        .target_directory
+        .join("release")
+        .join(binary)
+        .into_std_path_buf();
+    path.set_extension(std::env::consts::EXE_EXTENSION);
+
+    Ok(path)
+}
+
+/// Run a `cargo build` command, sending its output to `tx`
+async fn run_cargo_build(
+    mut cmd: tokio::process::Command,
+    tx: tokio::sync::mpsc::Sender<String>,
+) -> anyhow::Result<()> {
+    cmd.stderr(Stdio::piped());
+    cmd.stdout(Stdio::null());
+    let mut handle = cmd.spawn()?;
+    let reader = tokio::io::BufReader::new(handle.stderr.take().unwrap());
+    tokio::spawn(async move {
+        let mut lines = reader.lines();
+        while let Some(line) = lines.next_line().await.unwrap() {
+            let _ = tx
+                .send(line)
+                .await
+                .map_err(|error| error!(error = &error as &dyn std::error::Error));
+        }
+    });
+    let status = handle.wait().await?;
+    if !status.success() {
+        bail!("Build failed. Is the Cyndra runtime missing?");
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    async fn workspace_packages() -> Vec<Package> {
+        let manifest_path =
+            PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/resources/workspace/Cargo.toml");
+        let metadata = async_cargo_metadata(&manifest_path).await.unwrap();
+
+        find_cyndra_packages(&metadata).unwrap()
+    }
+
+    #[tokio::test]
+    async fn select_package_in_workspace() {
+        let packages = workspace_packages().await;
+
+        assert_eq!(
+            select_package(&packages, Some("alpha2")).unwrap().name,
+            "alpha2"
+        );
+        assert!(select_package(&packages, Some("shared"))
+            .unwrap_err()
+            .to_string()
+            .starts_with("Package 'shared' is not a Cyndra package in this workspace"));
+        assert!(select_package(&packages, None)
+            .unwrap_err()
+            .to_string()
+            .starts_with("Found multiple Cyndra packages in this workspace: alpha, alpha2."));
+        assert_eq!(select_package(&packages[..1], None).unwrap().name, "alpha");
+    }
+
+    #[tokio::test]
+    async fn select_binary_in_package() {
+        let packages = workspace_packages().await;
+        let package = select_package(&packages, Some("alpha")).unwrap();
+
+        assert_eq!(select_binary(package, None).unwrap(), "alpha");
+        assert_eq!(select_binary(package, Some("alpha")).unwrap(), "alpha");
+        assert!(select_binary(package, Some("beta"))
+            .unwrap_err()
+            .to_string()
+            .starts_with("Package 'alpha' does not have a binary target named 'beta'"));
+    }
+}
@@ -43,6 +43,7 @@ pub struct ProjectConfig {
     pub assets: Option<Vec<String>>,
     pub deploy: Option<ProjectDeployConfig>,
     pub build: Option<ProjectBuildConfig>,
+    pub secrets: Option<ProjectSecretsConfig>,
 }
 /// Deployment command config
 #[derive(Deserialize, Serialize, Default)]
@@ -52,6 +53,22 @@ pub struct ProjectDeployConfig {
     pub include: Option<Vec<String>>,
     /// Set to true to deny deployments with uncommited changes. (use `--allow-dirty` to override)
     pub deny_dirty: Option<bool>,
+    /// Maximum size of the deployment archive in MiB. Defaults to 200.
+    pub max_archive_size_mib: Option<u64>,
+    /// How files are compressed in the deployment archive. Defaults to deflate.
+    pub compression: Option<ArchiveCompression>,
+    /// Compression level, 0-9 for deflate and -7-22 for zstd. Uses the method's default if not set.
+    pub compression_level: Option<i64>,
+}
+/// Compression method for files in the deployment archive
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum ArchiveCompression {
+    #[default]
+    Deflate,
+    Zstd,
+    /// No compression
+    Stored,
 }
 /// Builder config
 #[derive(Deserialize, Serialize, Default)]
@@ -59,6 +76,44 @@ pub struct ProjectBuildConfig {
     /// Successor to `build_assets`.
     /// Patterns of files that should be copied from the build to the runtime container.
     pub assets: Option<Vec<String>>,
+    /// The cargo package to deploy if the workspace has several. (use `--package` to override)
+    pub package: Option<String>,
+    /// The binary target to deploy if the package has several. (use `--bin` to override)
+    pub binary: Option<String>,
+    /// Cargo features to activate. Defaults to the `cyndra` feature if the package has one.
+    pub features: Option<Vec<String>>,
+    /// Passed on to `cargo build`. Defaults to true if `features` is not set and the package has a `cyndra` feature.
+    pub no_default_features: Option<bool>,
+    /// Registry to push images to when building locally. (use `--registry` to override)
+    pub registry: Option<String>,
+}
+
+/// Secrets config
+#[derive(Deserialize, Serialize, Default)]
+pub struct ProjectSecretsConfig {
+    /// Where to load secrets from, with later sources overriding earlier ones.
+    /// Defaults to only the secrets file.
+    pub sources: Option<Vec<SecretSource>>,
+    /// The file for the `dotenv` source. Defaults to `.env` in the workspace root.
+    pub dotenv_file: Option<PathBuf>,
+    /// The prefix for the `env` source. Defaults to `cyndra_SECRET_`.
+    pub env_prefix: Option<String>,
+    /// Program and arguments for the `command` source.
+    /// It should print a JSON object of strings within a minute.
+    pub command: Option<Vec<String>>,
+}
+/// A place to load secrets from
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SecretSource {
+    /// Secrets.toml, Secrets.dev.toml, or the file given with `--secrets`
+    File,
+    /// A .env file
+    Dotenv,
+    /// Environment variables with a prefix, which is stripped from the secret name
+    Env,
+    /// The JSON output of an external command
+    Command,
 }
 
 /// .cyndra/config.toml schema (internal project-local config)
@@ -331,6 +386,42 @@ impl RequestContext {
             .and_then(|d| d.deny_dirty)
     }
 
+    /// # Panics
+    /// Panics if the project configuration has not been loaded.
+    pub fn deploy_config(&self) -> Option<&ProjectDeployConfig> {
+        self.project
+            .as_ref()
+            .unwrap()
+            .as_ref()
+            .unwrap()
+            .deploy
+            .as_ref()
+    }
+
+    /// # Panics
+    /// Panics if the project configuration has not been loaded.
+    pub fn build_config(&self) -> Option<&ProjectBuildConfig> {
+        self.project
+            .as_ref()
+            .unwrap()
+            .as_ref()
+            .unwrap()
+            .build
+            .as_ref()
+    }
+
+    /// # Panics
+    /// Panics if the project configuration has not been loaded.
+    pub fn secrets_config(&self) -> Option<&ProjectSecretsConfig> {
+        self.project
+            .as_ref()
+            .unwrap()
+            .as_ref()
+            .unwrap()
+            .secrets
+            .as_ref()
+    }
+
     /// Check if the current project id has been loaded.
     pub fn project_id_found(&self) -> bool {
         self.project_internal
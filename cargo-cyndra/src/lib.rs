@@ -5,10 +5,10 @@ mod init;
 mod provisioner_server;
 mod util;
 
-use std::collections::{BTreeMap, HashMap};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::ffi::OsString;
+use std::io::IsTerminal;
 use std::fs::{read_to_string, File};
-use std::io::{Read, Write};
 use std::net::{Ipv4Addr, SocketAddr};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
@@ -27,6 +27,7 @@ use ignore::WalkBuilder;
 use indicatif::ProgressBar;
 use indoc::formatdoc;
 use reqwest::header::HeaderMap;
+use serde::Serialize;
 use cyndra_api_client::CyndraApiClient;
 use cyndra_common::{
     constants::{
@@ -35,42 +36,78 @@ use cyndra_common::{
     },
     models::{
         auth::{KeyMessage, TokenMessage},
+        certificate::CertificateListResponse,
         deployment::{
-            BuildArgs, BuildArgsRust, BuildMeta, DeploymentRequest, DeploymentRequestBuildArchive,
-            DeploymentRequestImage, DeploymentResponse, DeploymentState, Environment,
-            GIT_STRINGS_MAX_LENGTH,
+            BuildArgs, BuildArgsRust, BuildMeta, DeploymentListResponse, DeploymentRequest,
+            DeploymentRequestBuildArchive, DeploymentRequestGitRepo, DeploymentRequestImage,
+            DeploymentResponse, DeploymentState, Environment, GIT_STRINGS_MAX_LENGTH,
         },
         error::ApiError,
-        log::LogItem,
-        project::ProjectUpdateRequest,
-        resource::ResourceType,
+        log::{LogItem, LogsResponse},
+        project::{ProjectListResponse, ProjectUpdateRequest},
+        resource::{ResourceListResponse, ResourceResponse, ResourceType},
+        secret::SecretKeysResponse,
+        team::{AddTeamMemberRequest, TeamListResponse, TeamResponse, TeamRole},
     },
-    tables::{deployments_table, get_certificates_table, get_projects_table, get_resource_tables},
+    secrets::SecretStore,
+    tables::{
+        deployments_table, get_certificates_table, get_projects_table, get_resource_tables,
+        get_secrets_list_table, get_team_members_tables, get_teams_table,
+    },
+    DatabaseInfo,
 };
 use strum::{EnumMessage, VariantArray};
-use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::io::{AsyncBufReadExt, AsyncWrite, AsyncWriteExt, BufReader};
 use tokio::time::{sleep, Duration};
 use tokio_tungstenite::tungstenite::Message;
 use tracing::{debug, error, info, trace};
 use tracing_subscriber::{fmt, prelude::*, registry, EnvFilter};
-use zip::write::FileOptions;
+use zip::{write::FileOptions, CompressionMethod};
 
 use crate::args::{
-    CertificateCommand, ConfirmationArgs, DeployArgs, DeploymentCommand, GenerateCommand, InitArgs,
-    LoginArgs, LogoutArgs, LogsArgs, ProjectCommand, ProjectUpdateCommand, ResourceCommand,
-    SecretsArgs, TableArgs, TemplateLocation,
+    CertificateCommand, ConfirmationArgs, DeployArgs, DeploymentCommand, GenerateCommand,
+    HealthCheckArgs, InitArgs, LoginArgs, LogoutArgs, LogsArgs, ProjectCommand,
+    ProjectUpdateCommand, ResourceCommand, SecretsArgs, SecretsCommand, TableArgs, TeamArgs,
+    TeamCommand, TemplateLocation,
+};
+pub use crate::args::{Command, OutputMode, ProjectArgs, RunArgs, CyndraArgs};
+use crate::builder::{
+    async_cargo_metadata, build_binary, build_workspace, find_cyndra_packages, select_binary,
+    select_package, BuiltService,
 };
-pub use crate::args::{Command, ProjectArgs, RunArgs, CyndraArgs};
-use crate::builder::{async_cargo_metadata, build_workspace, find_cyndra_packages, BuiltService};
-use crate::config::RequestContext;
-use crate::provisioner_server::{ProvApiState, ProvisionerServer};
+use crate::config::{ArchiveCompression, ProjectSecretsConfig, RequestContext, SecretSource};
+use crate::provisioner_server::{LocalProvisioner, ProvApiState, ProvisionerServer};
 use crate::util::{
-    bacon, check_and_warn_runtime_version, generate_completions, generate_manpage,
-    get_templates_schema, is_dirty, open_gh_issue, read_ws_until_text, update_cargo_cyndra,
+    archive::{
+        archive_name, excluded_entries, format_size, format_tree, looks_like_secret, Archive,
+        ArchiveEntry, ArchiveReason, LimitedWriter, LARGE_FILE_WARNING_BYTES,
+    },
+    bacon, check_and_warn_runtime_version,
+    deployments::{
+        build_args_changes, commits_between, find_previous_successful, resolve_git_ref, GitRefKind,
+    },
+    docker, generate_completions, generate_manpage, get_templates_schema, health, is_dirty,
+    logs::{select_range, LogDeduplicator, LogFilter},
+    open_gh_issue, read_ws_until_text, secrets, update_cargo_cyndra,
 };
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
+/// How many times in a row to try reconnecting to a log stream before giving up
+const LOG_STREAM_MAX_RETRIES: u32 = 5;
+/// Page size when searching the deployment history
+const DEPLOYMENT_HISTORY_PAGE_SIZE: u32 = 20;
+/// Time between rounds of health check probes
+const HEALTH_CHECK_INTERVAL_MILLIS: u64 = 2000;
+/// Upper limit for how long a single health check probe can take
+const HEALTH_CHECK_PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+/// Maximum size of the deployment archive if not set in Cyndra.toml
+const DEFAULT_MAX_ARCHIVE_SIZE_MIB: u64 = 200;
+/// How long a runtime drains its service when stopped, unless set with `cyndra_SHUTDOWN_TIMEOUT`
+const RUNTIME_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+/// Extra time for a runtime to exit after its drain timeout, before it is killed
+const RUNTIME_EXIT_GRACE: Duration = Duration::from_secs(2);
+
 /// Returns the args and whether the PATH arg of the init command was explicitly given
 pub fn parse_args() -> (CyndraArgs, bool) {
     let matches = CyndraArgs::command().get_matches();
@@ -110,6 +147,84 @@ pub fn setup_tracing(debug: bool) {
         .init();
 }
 
+/// Exit code of the CLI when a command fails, regardless of output mode
+pub const EXIT_CODE_ERROR: i32 = 1;
+
+/// Error format for `--output json`
+#[derive(Serialize)]
+struct JsonError {
+    message: String,
+    /// Set if the error was returned by the API
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status_code: Option<u16>,
+}
+
+/// Print the error of a failed command in the given output mode and exit with [`EXIT_CODE_ERROR`].
+/// In JSON mode, the error is printed to stdout so that it replaces the expected response.
+pub fn exit_with_error(error: anyhow::Error, output_mode: OutputMode) -> ! {
+    match output_mode {
+        OutputMode::Normal => eprintln!("Error: {error:?}"),
+        OutputMode::Json => {
+            let status_code = error
+                .downcast_ref::<ApiError>()
+                .map(|e| e.status().map(|s| s.as_u16()).unwrap_or_default());
+            let json = serde_json::json!({
+                "error": JsonError {
+                    message: format!("{error:#}"),
+                    status_code,
+                }
+            });
+            println!("{json:#}");
+        }
+    }
+
+    std::process::exit(EXIT_CODE_ERROR)
+}
+
+/// Print a response model for `--output json`
+fn print_json<T: Serialize + ?Sized>(value: &T) -> Result<()> {
+    println!(
+        "{}",
+        serde_json::to_string_pretty(value).context("failed to serialize output")?
+    );
+
+    Ok(())
+}
+
+/// Print a plain text response from the API, wrapped in an object in JSON mode
+fn print_message(output_mode: OutputMode, message: &str) -> Result<()> {
+    match output_mode {
+        OutputMode::Normal => println!("{message}"),
+        OutputMode::Json => print_json(&serde_json::json!({ "message": message }))?,
+    }
+
+    Ok(())
+}
+
+/// Hide passwords and secret values in the output of a resource, like the tables do
+fn redact_resource(resource: &mut ResourceResponse) {
+    let Some(output) = resource.output.as_object_mut() else {
+        return;
+    };
+    match resource.r#type {
+        ResourceType::Secrets => {
+            for value in output.values_mut() {
+                *value = "********".into();
+            }
+        }
+        ResourceType::DatabaseSharedPostgres
+        | ResourceType::DatabaseAwsRdsPostgres
+        | ResourceType::DatabaseAwsRdsMySql
+        | ResourceType::DatabaseAwsRdsMariaDB
+        | ResourceType::CacheRedis => {
+            if let Some(password) = output.get_mut("role_password") {
+                *password = "********".into();
+            }
+        }
+        _ => {}
+    }
+}
+
 #[derive(PartialEq)]
 pub enum Binary {
     CargoCyndra,
@@ -130,6 +245,7 @@ pub struct Cyndra {
     client: Option<CyndraApiClient>,
     /// Alter behaviour based on which CLI is used
     bin: Binary,
+    output_mode: OutputMode,
 }
 
 impl Cyndra {
@@ -144,26 +260,33 @@ impl Cyndra {
             ctx,
             client: None,
             bin,
+            output_mode: OutputMode::Normal,
         })
     }
 
     pub async fn run(mut self, args: CyndraArgs, provided_path_to_init: bool) -> Result<()> {
-        if matches!(args.cmd, Command::Resource(ResourceCommand::Dump { .. })) {
-            bail!("This command is not yet supported on the NEW platform (cyndra.dev).");
-        }
+        self.output_mode = args.output_mode;
 
         // All commands that call the API
         if matches!(
             args.cmd,
             Command::Init(..)
-                | Command::Deploy(..)
+                // a dry run only looks at local files
+                | Command::Deploy(DeployArgs { dry_run: false, .. })
                 | Command::Logs { .. }
                 | Command::Account
                 | Command::Login(..)
                 | Command::Logout(..)
                 | Command::Deployment(..)
-                | Command::Resource(..)
+                | Command::Resource(
+                    // Reset, Restore, and local Dump only touch the local Docker engine
+                    ResourceCommand::List { .. }
+                        | ResourceCommand::Delete { .. }
+                        | ResourceCommand::Dump { local: false, .. }
+                )
                 | Command::Certificate(..)
+                | Command::Secrets(..)
+                | Command::Team(..)
                 | Command::Project(..)
         ) || (
             // project linking on beta requires api client
@@ -207,10 +330,15 @@ impl Cyndra {
         // All commands that need to know which project is being handled
         if matches!(
             args.cmd,
-            Command::Deploy(..)
+            Command::Deploy(DeployArgs { dry_run: false, .. })
                 | Command::Deployment(..)
-                | Command::Resource(..)
+                | Command::Resource(
+                    ResourceCommand::List { .. }
+                        | ResourceCommand::Delete { .. }
+                        | ResourceCommand::Dump { local: false, .. }
+                )
                 | Command::Certificate(..)
+                | Command::Secrets(..)
                 | Command::Project(
                     // ProjectCommand::List does not need to know which project we are in
                     ProjectCommand::Create
@@ -218,6 +346,7 @@ impl Cyndra {
                         | ProjectCommand::Status
                         | ProjectCommand::Delete { .. }
                         | ProjectCommand::Link
+                        | ProjectCommand::Transfer { .. }
                 )
                 | Command::Logs { .. }
         ) {
@@ -256,6 +385,10 @@ impl Cyndra {
                 self.ctx.load_local(&args.project_args)?;
                 self.local_run(run_args, args.debug).await
             }
+            Command::Deploy(deploy_args) if deploy_args.dry_run => {
+                self.ctx.load_local(&args.project_args)?;
+                self.deploy_dry_run(deploy_args.secret_args.secrets)
+            }
             Command::Deploy(deploy_args) => self.deploy(deploy_args).await,
             Command::Logs(logs_args) => self.logs(logs_args).await,
             Command::Deployment(cmd) => match cmd {
@@ -266,6 +399,12 @@ impl Cyndra {
                 DeploymentCommand::Redeploy { id, tracking_args } => {
                     self.deployment_redeploy(id, tracking_args).await
                 }
+                DeploymentCommand::Diff { from, to } => self.deployment_diff(from, to).await,
+                DeploymentCommand::Rollback {
+                    steps,
+                    confirmation: ConfirmationArgs { yes },
+                    tracking_args,
+                } => self.deployment_rollback(steps, yes, tracking_args).await,
                 DeploymentCommand::Stop { tracking_args } => self.stop(tracking_args).await,
             },
             Command::Resource(cmd) => match cmd {
@@ -277,7 +416,34 @@ impl Cyndra {
                     resource_type,
                     confirmation: ConfirmationArgs { yes },
                 } => self.resource_delete(&resource_type, yes).await,
-                ResourceCommand::Dump { resource_type } => self.resource_dump(&resource_type).await,
+                ResourceCommand::Reset {
+                    confirmation: ConfirmationArgs { yes },
+                } => {
+                    self.ctx.load_local(&args.project_args)?;
+                    self.local_resources_reset(yes).await
+                }
+                ResourceCommand::Dump {
+                    resource_type,
+                    local,
+                    db_name,
+                    output,
+                } => {
+                    if local {
+                        self.ctx.load_local(&args.project_args)?;
+                    }
+                    self.resource_dump(resource_type, local, db_name, output)
+                        .await
+                }
+                ResourceCommand::Restore {
+                    resource_type,
+                    input,
+                    db_name,
+                    confirmation: ConfirmationArgs { yes },
+                } => {
+                    self.ctx.load_local(&args.project_args)?;
+                    self.local_resource_restore(resource_type, input, db_name, yes)
+                        .await
+                }
             },
             Command::Certificate(cmd) => match cmd {
                 CertificateCommand::Add { domain } => self.add_certificate(domain).await,
@@ -287,6 +453,30 @@ impl Cyndra {
                     confirmation: ConfirmationArgs { yes },
                 } => self.delete_certificate(domain, yes).await,
             },
+            Command::Secrets(cmd) => match cmd {
+                SecretsCommand::List { table } => self.secrets_list(table).await,
+                SecretsCommand::Set { secrets, from_file } => {
+                    self.secrets_set(secrets, from_file).await
+                }
+                SecretsCommand::Unset { keys } => self.secrets_unset(keys).await,
+                SecretsCommand::Diff { secret_args } => self.secrets_diff(secret_args).await,
+            },
+            Command::Team(cmd) => match cmd {
+                TeamCommand::List { table } => self.teams_list(table).await,
+                TeamCommand::Members { team_args, table } => {
+                    self.team_members(team_args, table).await
+                }
+                TeamCommand::Invite {
+                    email,
+                    role,
+                    team_args,
+                } => self.team_invite(team_args, email, role).await,
+                TeamCommand::Remove {
+                    user,
+                    team_args,
+                    confirmation: ConfirmationArgs { yes },
+                } => self.team_remove(team_args, user, yes).await,
+            },
             Command::Project(cmd) => match cmd {
                 ProjectCommand::Create => self.project_create().await,
                 ProjectCommand::Update(cmd) => match cmd {
@@ -296,6 +486,7 @@ impl Cyndra {
                 ProjectCommand::List { table, .. } => self.projects_list(table).await,
                 ProjectCommand::Delete(ConfirmationArgs { yes }) => self.project_delete(yes).await,
                 ProjectCommand::Link => Ok(()), // logic is done in `load_local`
+                ProjectCommand::Transfer { team, personal: _ } => self.project_transfer(team).await,
             },
             Command::Upgrade { preview } => update_cargo_cyndra(preview).await,
         }
@@ -758,7 +949,10 @@ impl Cyndra {
     async fn account(&self) -> Result<()> {
         let client = self.client.as_ref().unwrap();
         let user = client.get_current_user().await?;
-        print!("{}", user.to_string_colored());
+        match self.output_mode {
+            OutputMode::Normal => print!("{}", user.to_string_colored()),
+            OutputMode::Json => print_json(&user)?,
+        }
 
         Ok(())
     }
@@ -877,20 +1071,23 @@ impl Cyndra {
         let client = self.client.as_ref().unwrap();
         let pid = self.ctx.project_id();
         let res = client.stop_service(pid).await?;
-        println!("{res}");
 
         if tracking_args.no_follow {
-            return Ok(());
+            return print_message(self.output_mode, &res);
+        }
+        if self.output_mode == OutputMode::Normal {
+            println!("{res}");
         }
 
-        wait_with_spinner(2000, |_, pb| async move {
+        let deployment = wait_with_spinner(2000, |_, pb| async move {
             let deployment = client.get_current_deployment(pid).await?;
 
             let get_cleanup = |d: Option<DeploymentResponse>| {
                 move || {
-                    if let Some(d) = d {
+                    if let Some(d) = d.as_ref() {
                         eprintln!("{}", d.to_string_colored());
                     }
+                    d
                 }
             };
             let Some(deployment) = deployment else {
@@ -913,29 +1110,24 @@ impl Cyndra {
         })
         .await?;
 
+        if self.output_mode == OutputMode::Json {
+            print_json(&deployment)?;
+        }
+
         Ok(())
     }
 
     async fn logs(&self, args: LogsArgs) -> Result<()> {
-        if args.follow {
-            eprintln!("Streamed logs are not yet supported on the cyndra.dev platform.");
-            return Ok(());
-        }
-        if args.tail.is_some() | args.head.is_some() {
-            eprintln!("Fetching log ranges are not yet supported on the cyndra.dev platform.");
-            return Ok(());
-        }
         let client = self.client.as_ref().unwrap();
         let pid = self.ctx.project_id();
-        let logs = if args.all_deployments {
-            client.get_project_logs(pid).await?.logs
+        let deployment_id = if args.all_deployments {
+            None
         } else {
             let id = if args.latest {
                 // Find latest deployment (not always an active one)
                 let deployments = client.get_deployments(pid, 1, 1).await?.deployments;
                 let Some(most_recent) = deployments.into_iter().next() else {
-                    println!("No deployments found");
-                    return Ok(());
+                    return self.print_no_deployments();
                 };
                 eprintln!("Getting logs from: {}", most_recent.id);
                 most_recent.id
@@ -943,20 +1135,130 @@ impl Cyndra {
                 id
             } else {
                 let Some(current) = client.get_current_deployment(pid).await? else {
-                    println!("No deployments found");
-                    return Ok(());
+                    return self.print_no_deployments();
                 };
                 eprintln!("Getting logs from: {}", current.id);
                 current.id
             };
-            client.get_deployment_logs(pid, &id).await?.logs
+            Some(id)
         };
+        let logs = match deployment_id.as_deref() {
+            Some(id) => client.get_deployment_logs(pid, id).await?.logs,
+            None => client.get_project_logs(pid).await?.logs,
+        };
+
+        let filter = LogFilter {
+            since: args.since,
+            until: args.until,
+            sources: args.source,
+            grep: args.grep,
+            level: args.level,
+        };
+        // a followed stream resumes after the lines fetched here
+        let mut dedup = LogDeduplicator::default();
+        if args.follow {
+            for log in &logs {
+                dedup.is_new(log);
+            }
+        }
+        let logs = logs.into_iter().filter(|log| filter.matches(log)).collect();
+        let logs = select_range(logs, args.head, args.tail);
+
+        if !args.follow && self.output_mode == OutputMode::Json {
+            return print_json(&LogsResponse { logs });
+        }
         for log in logs {
-            if args.raw {
-                println!("{}", log.line);
-            } else {
-                println!("{log}");
+            self.print_log(&log, args.raw)?;
+        }
+        if args.follow {
+            self.follow_logs(deployment_id.as_deref(), dedup, &filter, args.raw)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Stream new log lines until the stream is closed by the server,
+    /// reconnecting and skipping already printed lines if the connection drops.
+    async fn follow_logs(
+        &self,
+        deployment_id: Option<&str>,
+        mut dedup: LogDeduplicator,
+        filter: &LogFilter,
+        raw: bool,
+    ) -> Result<()> {
+        let client = self.client.as_ref().unwrap();
+        let pid = self.ctx.project_id();
+        let mut retries = 0;
+
+        loop {
+            let since = dedup.last_timestamp().or(filter.since);
+            let stream = match deployment_id {
+                Some(id) => client.get_deployment_logs_ws(pid, id, since).await,
+                None => client.get_project_logs_ws(pid, since).await,
+            };
+            match stream {
+                Ok(mut stream) => {
+                    retries = 0;
+                    while let Some(msg) = stream.next().await {
+                        let msg = match msg {
+                            Ok(msg) => msg,
+                            Err(e) => {
+                                debug!(error = %e, "log stream failed");
+                                break;
+                            }
+                        };
+                        match msg {
+                            Message::Text(text) => {
+                                let log: LogItem = serde_json::from_str(&text)
+                                    .context("failed to parse log line from stream")?;
+                                if dedup.is_new(&log) && filter.matches(&log) {
+                                    self.print_log(&log, raw)?;
+                                }
+                            }
+                            // the server closes the stream when there will be no more logs
+                            Message::Close(_) => return Ok(()),
+                            _ => {}
+                        }
+                    }
+                }
+                Err(e) => {
+                    retries += 1;
+                    if retries > LOG_STREAM_MAX_RETRIES {
+                        return Err(e.context("failed to reconnect to the log stream"));
+                    }
+                    debug!(error = %e, "failed to connect to log stream");
+                }
             }
+
+            let backoff = Duration::from_secs(1 << retries.min(4));
+            eprintln!(
+                "{}",
+                format!(
+                    "Lost connection to the log stream, reconnecting in {}s...",
+                    backoff.as_secs()
+                )
+                .yellow()
+            );
+            sleep(backoff).await;
+        }
+    }
+
+    fn print_log(&self, log: &LogItem, raw: bool) -> Result<()> {
+        match self.output_mode {
+            OutputMode::Normal if raw => println!("{}", log.line),
+            OutputMode::Normal => println!("{log}"),
+            // one object per line, since the logs are printed as they arrive
+            OutputMode::Json => println!("{}", serde_json::to_string(log)?),
+        }
+
+        Ok(())
+    }
+
+    fn print_no_deployments(&self) -> Result<()> {
+        match self.output_mode {
+            OutputMode::Normal => println!("No deployments found"),
+            OutputMode::Json => print_json(&LogsResponse { logs: Vec::new() })?,
         }
 
         Ok(())
@@ -982,6 +1284,9 @@ impl Cyndra {
         } else {
             false
         };
+        if self.output_mode == OutputMode::Json {
+            return print_json(&DeploymentListResponse { deployments });
+        }
         let table = deployments_table(&deployments, table_args.raw);
 
         println!(
@@ -1005,14 +1310,20 @@ impl Cyndra {
             None => {
                 let d = client.get_current_deployment(pid).await?;
                 let Some(d) = d else {
-                    println!("No deployment found");
+                    match self.output_mode {
+                        OutputMode::Normal => println!("No deployment found"),
+                        OutputMode::Json => print_json(&None::<DeploymentResponse>)?,
+                    }
                     return Ok(());
                 };
                 Ok(d)
             }
         }?;
 
-        println!("{}", deployment.to_string_colored());
+        match self.output_mode {
+            OutputMode::Normal => println!("{}", deployment.to_string_colored()),
+            OutputMode::Json => print_json(&deployment)?,
+        }
 
         Ok(())
     }
@@ -1039,18 +1350,210 @@ impl Cyndra {
         let deployment = client.redeploy(pid, &deployment_id).await?;
 
         if tracking_args.no_follow {
-            println!("{}", deployment.to_string_colored());
-            return Ok(());
+            return self.print_deployment(&deployment);
         }
 
-        self.track_deployment_status_and_print_logs_on_fail(pid, &deployment.id, tracking_args.raw)
-            .await
+        let deployment = self
+            .track_deployment_status_and_print_logs_on_fail(pid, &deployment.id, tracking_args.raw)
+            .await?;
+        self.print_tracked_deployment(&deployment)
+    }
+
+    async fn deployment_diff(&self, from: String, to: String) -> Result<()> {
+        let client = self.client.as_ref().unwrap();
+        let pid = self.ctx.project_id();
+        let from = client.get_deployment(pid, &from).await?;
+        let to = client.get_deployment(pid, &to).await?;
+
+        let commit = |d: &DeploymentResponse| {
+            d.build_meta
+                .as_ref()
+                .and_then(|m| m.git_commit_id.clone())
+                .ok_or_else(|| {
+                    anyhow!(
+                        "Deployment {} has no git commit recorded. \
+                        It was probably deployed from outside a git repository.",
+                        d.id
+                    )
+                })
+        };
+        let from_commit = commit(&from)?;
+        let to_commit = commit(&to)?;
+        let repo = Repository::discover(self.ctx.working_directory())
+            .context("failed to find a git repository in the working directory")?;
+        let added = commits_between(&repo, &from_commit, &to_commit)?;
+        let removed = commits_between(&repo, &to_commit, &from_commit)?;
+        let build_args_changes = match (&from.build_args, &to.build_args) {
+            (Some(a), Some(b)) => Some(build_args_changes(a, b)),
+            _ => None,
+        };
+        // uncommitted changes are not in the repo, so they can't be compared
+        let dirty = [&from, &to]
+            .into_iter()
+            .filter(|d| d.build_meta.as_ref().and_then(|m| m.git_dirty) == Some(true))
+            .map(|d| d.id.as_str())
+            .collect::<Vec<_>>();
+
+        if self.output_mode == OutputMode::Json {
+            return print_json(&serde_json::json!({
+                "from": from.id,
+                "to": to.id,
+                "commits_added": added,
+                "commits_removed": removed,
+                "build_args_changes": build_args_changes,
+                "approximate": !dirty.is_empty(),
+            }));
+        }
+
+        for d in [&from, &to] {
+            let meta = d.build_meta.as_ref().map(ToString::to_string);
+            println!(
+                "{} {}",
+                d.id.as_str().bold(),
+                meta.unwrap_or_default().dim()
+            );
+        }
+        println!();
+        if added.is_empty() && removed.is_empty() {
+            println!("Both deployments were built from the same commit");
+        }
+        for c in &added {
+            println!("{}", format!("+ {:.7} {}", c.id, c.summary).green());
+        }
+        for c in &removed {
+            println!("{}", format!("- {:.7} {}", c.id, c.summary).red());
+        }
+        println!();
+        match build_args_changes {
+            None => println!("Build args: not recorded for both deployments"),
+            Some(changes) if changes.is_empty() => println!("Build args: unchanged"),
+            Some(changes) => {
+                println!("Build args changed:");
+                for c in changes {
+                    println!("  {}: {} -> {}", c.field, c.from, c.to);
+                }
+            }
+        }
+        if !dirty.is_empty() {
+            eprintln!();
+            eprintln!(
+                "{}",
+                formatdoc!(
+                    "
+                    Note:
+                        {} built from a tree with uncommitted changes.
+                        Those changes are not in git, so this diff is approximate.",
+                    if dirty.len() == 1 {
+                        format!("Deployment {} was", dirty[0])
+                    } else {
+                        "Both deployments were".to_owned()
+                    }
+                )
+                .yellow()
+            );
+        }
+
+        Ok(())
+    }
+
+    async fn deployment_rollback(
+        &self,
+        steps: u32,
+        no_confirm: bool,
+        tracking_args: DeploymentTrackingArgs,
+    ) -> Result<()> {
+        let client = self.client.as_ref().unwrap();
+        let pid = self.ctx.project_id();
+
+        let current = client.get_current_deployment(pid).await?;
+        let mut deployments = Vec::new();
+        for page in 1.. {
+            let batch = client
+                .get_deployments(pid, page, DEPLOYMENT_HISTORY_PAGE_SIZE as i32)
+                .await?
+                .deployments;
+            let last_page = batch.len() < DEPLOYMENT_HISTORY_PAGE_SIZE as usize;
+            deployments.extend(batch);
+            if last_page
+                || find_previous_successful(&deployments, current.as_ref(), steps as usize)
+                    .is_some()
+            {
+                break;
+            }
+        }
+        let Some(target) = find_previous_successful(&deployments, current.as_ref(), steps as usize)
+        else {
+            bail!("Could not find a successful deployment {steps} step(s) back to roll back to");
+        };
+
+        let created_at: chrono::DateTime<chrono::Local> = target.created_at.into();
+        eprintln!(
+            "Rolling back to deployment {} from {}",
+            target.id.as_str().bold(),
+            created_at.to_rfc3339_opts(chrono::SecondsFormat::Secs, false)
+        );
+        if let Some(meta) = &target.build_meta {
+            if let Some(branch) = &meta.git_branch {
+                eprintln!("  Branch:  {branch}");
+            }
+            if let Some(commit) = &meta.git_commit_id {
+                eprintln!("  Commit:  {commit}");
+            }
+            if let Some(msg) = &meta.git_commit_msg {
+                eprintln!("  Message: {msg}");
+            }
+            if meta.git_dirty == Some(true) {
+                eprintln!("  (built from a tree with uncommitted changes)");
+            }
+        }
+
+        if !no_confirm {
+            eprintln!(
+                "{}",
+                formatdoc!(
+                    "
+                    WARNING:
+                        This will replace the current deployment{}.",
+                    current
+                        .as_ref()
+                        .map(|c| format!(" {}", c.id))
+                        .unwrap_or_default()
+                )
+                .bold()
+                .red()
+            );
+            if !Confirm::with_theme(&ColorfulTheme::default())
+                .with_prompt("Are you sure?")
+                .default(false)
+                .interact()
+                .unwrap()
+            {
+                return Ok(());
+            }
+        }
+
+        let deployment = client.redeploy(pid, &target.id).await?;
+
+        if tracking_args.no_follow {
+            return self.print_deployment(&deployment);
+        }
+
+        let deployment = self
+            .track_deployment_status_and_print_logs_on_fail(pid, &deployment.id, tracking_args.raw)
+            .await?;
+        self.print_tracked_deployment(&deployment)
     }
 
     async fn resources_list(&self, table_args: TableArgs, show_secrets: bool) -> Result<()> {
         let client = self.client.as_ref().unwrap();
         let pid = self.ctx.project_id();
-        let resources = client.get_service_resources(pid).await?.resources;
+        let mut resources = client.get_service_resources(pid).await?.resources;
+        if self.output_mode == OutputMode::Json {
+            if !show_secrets {
+                resources.iter_mut().for_each(redact_resource);
+            }
+            return print_json(&ResourceListResponse { resources });
+        }
         let table = get_resource_tables(resources.as_slice(), pid, table_args.raw, show_secrets);
 
         println!("{table}");
@@ -1087,7 +1590,7 @@ impl Cyndra {
         let msg = client
             .delete_service_resource(self.ctx.project_id(), resource_type)
             .await?;
-        println!("{msg}");
+        print_message(self.output_mode, &msg)?;
 
         eprintln!(
             "{}",
@@ -1102,38 +1605,129 @@ impl Cyndra {
         Ok(())
     }
 
-    async fn resource_dump(&self, _resource_type: &ResourceType) -> Result<()> {
-        unimplemented!();
-        // let client = self.client.as_ref().unwrap();
-        // let bytes = client...;
-        // std::io::stdout().write_all(&bytes).unwrap();
-        // Ok(())
-    }
+    async fn local_resources_reset(&self, no_confirm: bool) -> Result<()> {
+        let project_name = self.ctx.project_name();
 
-    async fn list_certificates(&self, table_args: TableArgs) -> Result<()> {
-        let client = self.client.as_ref().unwrap();
-        let certs = client
-            .list_certificates(self.ctx.project_id())
-            .await?
-            .certificates;
+        if !no_confirm {
+            eprintln!(
+                "{}",
+                formatdoc!(
+                    "
+                WARNING:
+                    Are you sure you want to remove the local containers and volumes of '{}'?
+                    All data in its local databases will be lost.",
+                    project_name
+                )
+                .bold()
+                .red()
+            );
+            if !Confirm::with_theme(&ColorfulTheme::default())
+                .with_prompt("Are you sure?")
+                .default(false)
+                .interact()
+                .unwrap()
+            {
+                return Ok(());
+            }
+        }
 
-        let table = get_certificates_table(certs.as_ref(), table_args.raw);
-        println!("{}", table);
+        let removed = LocalProvisioner::new()?.reset(project_name).await.context(
+            "Failed to reset local resources. Make sure that a Docker engine is running.",
+        )?;
+
+        if removed.is_empty() {
+            println!("No local resources found for '{project_name}'");
+        }
+        for name in removed {
+            println!("Removed {name}");
+        }
 
         Ok(())
     }
-    async fn add_certificate(&self, domain: String) -> Result<()> {
-        let client = self.client.as_ref().unwrap();
-        let cert = client
-            .add_certificate(self.ctx.project_id(), domain.clone())
-            .await?;
 
-        println!("Added certificate for {}", cert.subject);
+    async fn resource_dump(
+        &self,
+        resource_type: ResourceType,
+        local: bool,
+        db_name: Option<String>,
+        output: Option<PathBuf>,
+    ) -> Result<()> {
+        if !is_database(&resource_type) {
+            bail!("Only database resources can be dumped. Got '{resource_type}'.");
+        }
+
+        // Dump into a tempfile next to the output, so a failed dump does not clobber an existing one
+        let temp = output
+            .as_ref()
+            .map(|path| {
+                let dir = path
+                    .parent()
+                    .filter(|dir| !dir.as_os_str().is_empty())
+                    .unwrap_or(Path::new("."));
+                tempfile::Builder::new()
+                    .prefix(".cyndra-dump")
+                    .tempfile_in(dir)
+                    .with_context(|| format!("failed to create a tempfile in {}", dir.display()))
+            })
+            .transpose()?;
+        let mut writer: Box<dyn AsyncWrite + Unpin + Send> = match temp {
+            Some(ref temp) => Box::new(tokio::fs::File::from_std(
+                temp.reopen().context("failed to open the dump tempfile")?,
+            )),
+            None => Box::new(tokio::io::stdout()),
+        };
+
+        if local {
+            LocalProvisioner::new()?
+                .dump_database(
+                    self.ctx.project_name(),
+                    resource_type.clone(),
+                    db_name,
+                    &mut writer,
+                )
+                .await
+                .context(
+                    "Failed to dump local database. Make sure that a Docker engine is running.",
+                )?;
+        } else {
+            let client = self.client.as_ref().unwrap();
+            let resource = client
+                .get_service_resources(self.ctx.project_id())
+                .await?
+                .resources
+                .into_iter()
+                .find(|r| r.r#type == resource_type)
+                .with_context(|| {
+                    format!("This project does not have a {resource_type} resource")
+                })?;
+            let info: DatabaseInfo = serde_json::from_value(resource.output)
+                .context("failed to read the connection info of the resource")?;
+
+            dump_remote_database(&info, &mut writer).await?;
+        }
+        writer.flush().await?;
+        drop(writer);
+
+        if let (Some(temp), Some(path)) = (temp, output) {
+            temp.persist(&path)
+                .with_context(|| format!("failed to write {}", path.display()))?;
+            eprintln!("Wrote dump of {resource_type} to {}", path.display());
+        }
 
         Ok(())
     }
-    async fn delete_certificate(&self, domain: String, no_confirm: bool) -> Result<()> {
-        let client = self.client.as_ref().unwrap();
+
+    async fn local_resource_restore(
+        &self,
+        resource_type: ResourceType,
+        input: PathBuf,
+        db_name: Option<String>,
+        no_confirm: bool,
+    ) -> Result<()> {
+        if !is_database(&resource_type) {
+            bail!("Only database resources can be restored. Got '{resource_type}'.");
+        }
+        let project_name = self.ctx.project_name();
 
         if !no_confirm {
             eprintln!(
@@ -1141,8 +1735,321 @@ impl Cyndra {
                 formatdoc!(
                     "
                 WARNING:
-                    Delete the certificate for {}?",
-                    domain
+                    Are you sure you want to run {} against the local {} of '{}'?
+                    Existing data in it may be overwritten.",
+                    input.display(),
+                    resource_type,
+                    project_name
+                )
+                .bold()
+                .red()
+            );
+            if !Confirm::with_theme(&ColorfulTheme::default())
+                .with_prompt("Are you sure?")
+                .default(false)
+                .interact()
+                .unwrap()
+            {
+                return Ok(());
+            }
+        }
+
+        let dump = tokio::fs::read(&input)
+            .await
+            .with_context(|| format!("failed to read {}", input.display()))?;
+        LocalProvisioner::new()?
+            .restore_database(project_name, resource_type.clone(), db_name, dump)
+            .await
+            .context(
+                "Failed to restore local database. Make sure that `cyndra run` has started it.",
+            )?;
+
+        eprintln!(
+            "Restored {} into the local {resource_type}",
+            input.display()
+        );
+
+        Ok(())
+    }
+
+    /// Find a team by ID or name, or the only team the user is in if none is given
+    async fn find_team(&self, team: Option<String>) -> Result<TeamResponse> {
+        let client = self.client.as_ref().unwrap();
+        let mut teams = client.get_teams_list().await?.teams;
+
+        match team {
+            Some(team) => teams
+                .into_iter()
+                .find(|t| t.id == team || t.name == team)
+                .ok_or_else(|| anyhow!("You are not a member of a team named '{team}'")),
+            None => match teams.len() {
+                0 => bail!("You are not a member of any team"),
+                1 => Ok(teams.remove(0)),
+                _ => bail!(
+                    "You are a member of multiple teams. Use `--team` to choose one of: {}",
+                    teams
+                        .iter()
+                        .map(|t| t.name.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+            },
+        }
+    }
+
+    async fn teams_list(&self, table_args: TableArgs) -> Result<()> {
+        let client = self.client.as_ref().unwrap();
+        let teams = client.get_teams_list().await?.teams;
+
+        if self.output_mode == OutputMode::Json {
+            return print_json(&TeamListResponse { teams });
+        }
+        if teams.is_empty() {
+            println!("You are not a member of any team.");
+            return Ok(());
+        }
+        println!("{}", get_teams_table(&teams, table_args.raw));
+
+        Ok(())
+    }
+
+    async fn team_members(&self, team_args: TeamArgs, table_args: TableArgs) -> Result<()> {
+        let client = self.client.as_ref().unwrap();
+        let team = self.find_team(team_args.team).await?;
+        let members = client.get_team_members(&team.id).await?;
+
+        if self.output_mode == OutputMode::Json {
+            return print_json(&members);
+        }
+        println!("Members of team '{}'", team.name);
+        println!("{}", get_team_members_tables(&members, table_args.raw));
+
+        Ok(())
+    }
+
+    async fn team_invite(&self, team_args: TeamArgs, email: String, role: TeamRole) -> Result<()> {
+        let client = self.client.as_ref().unwrap();
+        let team = self.find_team(team_args.team).await?;
+
+        client
+            .add_team_member(
+                &team.id,
+                AddTeamMemberRequest {
+                    user_id: None,
+                    email: Some(email.clone()),
+                    role: Some(role.clone()),
+                },
+            )
+            .await?;
+
+        print_message(
+            self.output_mode,
+            &format!("Invited {email} to team '{}' as {role}", team.name),
+        )?;
+
+        Ok(())
+    }
+
+    async fn team_remove(&self, team_args: TeamArgs, user: String, no_confirm: bool) -> Result<()> {
+        let client = self.client.as_ref().unwrap();
+        let team = self.find_team(team_args.team).await?;
+        let members = client.get_team_members(&team.id).await?.members;
+
+        let Some(member) = members
+            .into_iter()
+            .find(|m| m.user_id == user || m.email.as_deref() == Some(user.as_str()))
+        else {
+            bail!("'{user}' is not a member of team '{}'", team.name);
+        };
+
+        if !no_confirm {
+            eprintln!(
+                "{}",
+                formatdoc!(
+                    "
+                    WARNING:
+                        Are you sure you want to remove {user} from team '{}'?
+                        They will lose access to all projects in the team.",
+                    team.name
+                )
+                .bold()
+                .red()
+            );
+            if !Confirm::with_theme(&ColorfulTheme::default())
+                .with_prompt("Are you sure?")
+                .default(false)
+                .interact()
+                .unwrap()
+            {
+                return Ok(());
+            }
+        }
+
+        client.remove_team_member(&team.id, &member.user_id).await?;
+
+        print_message(
+            self.output_mode,
+            &format!("Removed {user} from team '{}'", team.name),
+        )?;
+
+        Ok(())
+    }
+
+    async fn get_remote_secrets(&self) -> Result<SecretStore> {
+        let client = self.client.as_ref().unwrap();
+        let res = client.get_secrets(self.ctx.project_id()).await?;
+
+        serde_json::from_value(res.output).context("failed to deserialize secrets")
+    }
+
+    async fn secrets_list(&self, table_args: TableArgs) -> Result<()> {
+        let secrets = self.get_remote_secrets().await?;
+
+        if self.output_mode == OutputMode::Json {
+            let keys = secrets.into_iter().map(|(k, _)| k).collect();
+            return print_json(&SecretKeysResponse { keys });
+        }
+        let table = get_secrets_list_table(&secrets, table_args.raw);
+        println!("{table}");
+
+        Ok(())
+    }
+
+    async fn secrets_set(
+        &self,
+        secrets: Vec<(String, Option<String>)>,
+        from_file: Option<PathBuf>,
+    ) -> Result<()> {
+        let client = self.client.as_ref().unwrap();
+        let bare_keys = secrets.iter().filter(|(_, v)| v.is_none()).count();
+        if from_file.is_some() && bare_keys != 1 {
+            bail!("--from-file needs exactly one secret given as KEY without a value");
+        }
+        if bare_keys > 1 && !std::io::stdin().is_terminal() {
+            bail!("Only one secret value can be read from stdin at a time");
+        }
+        let secrets = secrets
+            .into_iter()
+            .map(|(key, value)| {
+                let value = match value {
+                    Some(value) => value,
+                    None => secrets::read_secret_value(&key, from_file.as_deref())?,
+                };
+                Ok((key, value))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let keys = secrets
+            .iter()
+            .map(|(k, _)| k.as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let res = client
+            .set_secrets(self.ctx.project_id(), secrets.into_iter().collect())
+            .await?;
+
+        match self.output_mode {
+            OutputMode::Normal => println!("Set secrets: {keys}"),
+            OutputMode::Json => print_json(&res)?,
+        }
+
+        Ok(())
+    }
+
+    async fn secrets_unset(&self, keys: Vec<String>) -> Result<()> {
+        let client = self.client.as_ref().unwrap();
+        let remote = self.get_remote_secrets().await?;
+        if let Some(key) = keys.iter().find(|k| remote.get(k).is_none()) {
+            bail!("The project does not have a secret named '{key}'");
+        }
+
+        let joined = keys.join(", ");
+        let res = client.unset_secrets(self.ctx.project_id(), keys).await?;
+
+        match self.output_mode {
+            OutputMode::Normal => println!("Removed secrets: {joined}"),
+            OutputMode::Json => print_json(&res)?,
+        }
+
+        Ok(())
+    }
+
+    async fn secrets_diff(&self, secret_args: SecretsArgs) -> Result<()> {
+        let local = Cyndra::get_secrets(
+            &secret_args,
+            self.ctx.working_directory(),
+            false,
+            self.ctx.secrets_config(),
+        )
+        .await?
+        .unwrap_or_default();
+        let remote = self
+            .get_remote_secrets()
+            .await?
+            .into_iter()
+            .collect::<HashMap<_, _>>();
+
+        let diff = secrets::SecretsDiff::new(&local, &remote);
+        if self.output_mode == OutputMode::Json {
+            return print_json(&diff);
+        }
+        if diff.is_empty() {
+            println!("Local secrets match the project's secrets");
+            return Ok(());
+        }
+        for key in diff.added {
+            println!("{}", format!("+ {key} (only local)").green());
+        }
+        for key in diff.removed {
+            println!("{}", format!("- {key} (only in project)").red());
+        }
+        for key in diff.changed {
+            println!("{}", format!("~ {key} (changed)").yellow());
+        }
+
+        Ok(())
+    }
+
+    async fn list_certificates(&self, table_args: TableArgs) -> Result<()> {
+        let client = self.client.as_ref().unwrap();
+        let certs = client
+            .list_certificates(self.ctx.project_id())
+            .await?
+            .certificates;
+
+        if self.output_mode == OutputMode::Json {
+            return print_json(&CertificateListResponse {
+                certificates: certs,
+            });
+        }
+        let table = get_certificates_table(certs.as_ref(), table_args.raw);
+        println!("{}", table);
+
+        Ok(())
+    }
+    async fn add_certificate(&self, domain: String) -> Result<()> {
+        let client = self.client.as_ref().unwrap();
+        let cert = client
+            .add_certificate(self.ctx.project_id(), domain.clone())
+            .await?;
+
+        match self.output_mode {
+            OutputMode::Normal => println!("Added certificate for {}", cert.subject),
+            OutputMode::Json => print_json(&cert)?,
+        }
+
+        Ok(())
+    }
+    async fn delete_certificate(&self, domain: String, no_confirm: bool) -> Result<()> {
+        let client = self.client.as_ref().unwrap();
+
+        if !no_confirm {
+            eprintln!(
+                "{}",
+                formatdoc!(
+                    "
+                WARNING:
+                    Delete the certificate for {}?",
+                    domain
                 )
                 .bold()
                 .red()
@@ -1160,12 +2067,72 @@ impl Cyndra {
         let msg = client
             .delete_certificate(self.ctx.project_id(), domain.clone())
             .await?;
-        println!("{msg}");
+        print_message(self.output_mode, &msg)?;
 
         Ok(())
     }
 
-    fn get_secrets(
+    /// Load secrets from the sources in Cyndra.toml, with later sources overriding earlier ones.
+    /// Returns `None` if none of the sources had any secrets.
+    async fn get_secrets(
+        args: &SecretsArgs,
+        workspace_root: &Path,
+        dev: bool,
+        config: Option<&ProjectSecretsConfig>,
+    ) -> Result<Option<HashMap<String, String>>> {
+        let sources = config
+            .and_then(|c| c.sources.as_deref())
+            .unwrap_or(&[SecretSource::File]);
+        if args.secrets.is_some() && !sources.contains(&SecretSource::File) {
+            bail!("`--secrets` was given, but 'file' is not in `secrets.sources` in Cyndra.toml");
+        }
+
+        let mut merged: Option<HashMap<String, String>> = None;
+        for source in sources {
+            let loaded = match source {
+                SecretSource::File => Cyndra::get_secrets_file(args, workspace_root, dev)?,
+                SecretSource::Dotenv => {
+                    let path = workspace_root.join(
+                        config
+                            .and_then(|c| c.dotenv_file.as_deref())
+                            .unwrap_or(Path::new(".env")),
+                    );
+                    if path.is_file() {
+                        trace!("Loading secrets from {}", path.display());
+                        let content = read_to_string(&path)?;
+                        Some(
+                            secrets::parse_dotenv(&content)
+                                .with_context(|| format!("Failed to parse {}", path.display()))?,
+                        )
+                    } else {
+                        trace!("No dotenv file was found");
+                        None
+                    }
+                }
+                SecretSource::Env => {
+                    let prefix = config
+                        .and_then(|c| c.env_prefix.as_deref())
+                        .unwrap_or(secrets::DEFAULT_ENV_PREFIX);
+                    Some(secrets::env_secrets(std::env::vars(), prefix)).filter(|s| !s.is_empty())
+                }
+                SecretSource::Command => {
+                    let Some(command) = config.and_then(|c| c.command.as_deref()) else {
+                        bail!("'command' is in `secrets.sources`, but `secrets.command` is not set in Cyndra.toml");
+                    };
+                    Some(secrets::command_secrets(command, workspace_root).await?)
+                }
+            };
+
+            if let Some(loaded) = loaded {
+                trace!(?source, keys = ?loaded.keys(), "loaded secrets");
+                merged.get_or_insert_with(HashMap::new).extend(loaded);
+            }
+        }
+
+        Ok(merged)
+    }
+
+    fn get_secrets_file(
         args: &SecretsArgs,
         workspace_root: &Path,
         dev: bool,
@@ -1221,28 +2188,32 @@ impl Cyndra {
             working_directory.display()
         );
 
-        build_workspace(working_directory, run_args.release, tx, false).await
+        build_workspace(
+            working_directory,
+            run_args.release,
+            tx,
+            false,
+            run_args.package.as_deref(),
+        )
+        .await
     }
 
-    fn find_available_port(run_args: &mut RunArgs) {
-        let original_port = run_args.port;
-        for port in (run_args.port..=u16::MAX).step_by(10) {
-            if !portpicker::is_free_tcp(port) {
-                continue;
-            }
-            run_args.port = port;
-            break;
+    /// Find a free port to start a service on, starting the search at `port`.
+    /// Ports in `taken` have already been given to other services in this run.
+    fn find_available_port(port: u16, taken: &[u16]) -> u16 {
+        let available = (port..=u16::MAX)
+            .step_by(10)
+            .find(|p| !taken.contains(p) && portpicker::is_free_tcp(*p))
+            .unwrap_or(port);
+
+        if available != port {
+            eprintln!("Port {} is already in use. Using port {}.", port, available);
         }
 
-        if run_args.port != original_port {
-            eprintln!(
-                "Port {} is already in use. Using port {}.",
-                original_port, run_args.port,
-            )
-        };
+        available
     }
 
-    async fn local_run(&self, mut run_args: RunArgs, debug: bool) -> Result<()> {
+    async fn local_run(&self, run_args: RunArgs, debug: bool) -> Result<()> {
         let project_name = self.ctx.project_name().to_owned();
         let working_directory = self.ctx.working_directory();
 
@@ -1257,24 +2228,148 @@ impl Cyndra {
         }
 
         let services = self.pre_local_run(&run_args).await?;
-        let service = services
-            .first()
-            .expect("at least one cyndra service")
-            .to_owned();
 
+        let secrets = Cyndra::get_secrets(
+            &run_args.secret_args,
+            working_directory,
+            true,
+            self.ctx.secrets_config(),
+        )
+        .await?
+        .unwrap_or_default();
+
+        // All services in the workspace share one provisioner, and therefore the same resources
+        let api_port = portpicker::pick_unused_port()
+            .expect("failed to find available port for local provisioner server");
+        let api_addr = SocketAddr::new(Ipv4Addr::LOCALHOST.into(), api_port);
+        let state = Arc::new(ProvApiState {
+            project_name: project_name.clone(),
+            working_directory: working_directory.to_path_buf(),
+            secrets,
+        });
+        tokio::spawn(async move { ProvisionerServer::run(state, &api_addr).await });
+
+        // With several services running, log lines are labelled with the package they came from
+        let label_with_package = services.len() > 1;
+        let mut ports = Vec::new();
+        let mut runtimes = Vec::new();
+        for (i, service) in services.iter().enumerate() {
+            let port = Cyndra::find_available_port(run_args.port.saturating_add(i as u16), &ports);
+            ports.push(port);
+            let log_source = if label_with_package {
+                service.package_name.clone()
+            } else {
+                "app".to_owned()
+            };
+
+            let runtime = self
+                .spawn_runtime(service, &run_args, port, api_port, log_source, debug)
+                .await?;
+            runtimes.push((service.package_name.clone(), runtime));
+        }
+
+        #[cfg(target_family = "unix")]
+        let exit_result = {
+            let mut sigterm_notif =
+                tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+                    .expect("Can not get the SIGTERM signal receptor");
+            let mut sigint_notif =
+                tokio::signal::unix::signal(tokio::signal::unix::SignalKind::interrupt())
+                    .expect("Can not get the SIGINT signal receptor");
+            tokio::select! {
+                exit_result = wait_for_any_runtime(&mut runtimes) => {
+                    Some(exit_result)
+                }
+                _ = sigterm_notif.recv() => {
+                    eprintln!("Received SIGTERM. Killing the runtime...");
+                    None
+                },
+                _ = sigint_notif.recv() => {
+                    eprintln!("Received SIGINT. Killing the runtime...");
+                    None
+                }
+            }
+        };
+        #[cfg(target_family = "windows")]
+        let exit_result = {
+            let mut ctrl_break_notif = tokio::signal::windows::ctrl_break()
+                .expect("Can not get the CtrlBreak signal receptor");
+            let mut ctrl_c_notif =
+                tokio::signal::windows::ctrl_c().expect("Can not get the CtrlC signal receptor");
+            let mut ctrl_close_notif = tokio::signal::windows::ctrl_close()
+                .expect("Can not get the CtrlClose signal receptor");
+            let mut ctrl_logoff_notif = tokio::signal::windows::ctrl_logoff()
+                .expect("Can not get the CtrlLogoff signal receptor");
+            let mut ctrl_shutdown_notif = tokio::signal::windows::ctrl_shutdown()
+                .expect("Can not get the CtrlShutdown signal receptor");
+            tokio::select! {
+                exit_result = wait_for_any_runtime(&mut runtimes) => {
+                    Some(exit_result)
+                }
+                _ = ctrl_break_notif.recv() => {
+                    eprintln!("Received ctrl-break.");
+                    None
+                },
+                _ = ctrl_c_notif.recv() => {
+                    eprintln!("Received ctrl-c.");
+                    None
+                },
+                _ = ctrl_close_notif.recv() => {
+                    eprintln!("Received ctrl-close.");
+                    None
+                },
+                _ = ctrl_logoff_notif.recv() => {
+                    eprintln!("Received ctrl-logoff.");
+                    None
+                },
+                _ = ctrl_shutdown_notif.recv() => {
+                    eprintln!("Received ctrl-shutdown.");
+                    None
+                }
+            }
+        };
+        // Any runtimes that are still running are killed when dropped
+        match exit_result {
+            Some((package_name, Ok(exit_status))) => {
+                bail!(
+                    "Runtime process of {} exited with code {}",
+                    package_name,
+                    exit_status.code().unwrap_or_default()
+                );
+            }
+            Some((package_name, Err(e))) => {
+                bail!("Failed to wait for runtime process of {package_name} to exit: {e}");
+            }
+            None => {
+                futures::future::try_join_all(
+                    runtimes
+                        .into_iter()
+                        .map(|(package_name, runtime)| stop_runtime(package_name, runtime)),
+                )
+                .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Start the runtime of one service along with tasks for printing its logs and health checking it
+    async fn spawn_runtime(
+        &self,
+        service: &BuiltService,
+        run_args: &RunArgs,
+        port: u16,
+        api_port: u16,
+        log_source: String,
+        debug: bool,
+    ) -> Result<tokio::process::Child> {
         trace!(path = ?service.executable_path, "runtime executable");
 
-        let secrets = Cyndra::get_secrets(&run_args.secret_args, working_directory, true)?
-            .unwrap_or_default();
-        Cyndra::find_available_port(&mut run_args);
         if let Some(warning) = check_and_warn_runtime_version(&service.executable_path).await? {
             eprint!("{}", warning);
         }
 
         let runtime_executable = service.executable_path.clone();
-        let api_port = portpicker::pick_unused_port()
-            .expect("failed to find available port for local provisioner server");
-        let api_addr = SocketAddr::new(Ipv4Addr::LOCALHOST.into(), api_port);
         let healthz_port = portpicker::pick_unused_port()
             .expect("failed to find available port for runtime health check");
         let ip = if run_args.external {
@@ -1283,27 +2378,21 @@ impl Cyndra {
             Ipv4Addr::LOCALHOST
         };
 
-        let state = Arc::new(ProvApiState {
-            project_name: project_name.clone(),
-            secrets,
-        });
-        tokio::spawn(async move { ProvisionerServer::run(state, &api_addr).await });
-
         println!(
             "\n    {} {} on http://{}:{}\n",
             "Starting".bold().green(),
             service.package_name,
             ip,
-            run_args.port,
+            port,
         );
 
         let mut envs = vec![
             ("cyndra_BETA", "true".to_owned()),
             ("cyndra_PROJECT_ID", "proj_LOCAL".to_owned()),
-            ("cyndra_PROJECT_NAME", project_name),
+            ("cyndra_PROJECT_NAME", self.ctx.project_name().to_owned()),
             ("cyndra_ENV", Environment::Local.to_string()),
             ("cyndra_RUNTIME_IP", ip.to_string()),
-            ("cyndra_RUNTIME_PORT", run_args.port.to_string()),
+            ("cyndra_RUNTIME_PORT", port.to_string()),
             ("cyndra_HEALTHZ_PORT", healthz_port.to_string()),
             ("cyndra_API", format!("http://127.0.0.1:{}", api_port)),
         ];
@@ -1336,12 +2425,13 @@ impl Cyndra {
                 .context("child process did not have a handle to stdout")?,
         )
         .lines();
+        let source = log_source.clone();
         tokio::spawn(async move {
             while let Some(line) = stdout_reader.next_line().await.unwrap() {
                 if raw {
                     println!("{}", line);
                 } else {
-                    let log_item = LogItem::new(Utc::now(), "app".to_owned(), line);
+                    let log_item = LogItem::new(Utc::now(), source.clone(), line);
                     println!("{log_item}");
                 }
             }
@@ -1353,12 +2443,13 @@ impl Cyndra {
                 .context("child process did not have a handle to stderr")?,
         )
         .lines();
+        let source = log_source;
         tokio::spawn(async move {
             while let Some(line) = stderr_reader.next_line().await.unwrap() {
                 if raw {
                     println!("{}", line);
                 } else {
-                    let log_item = LogItem::new(Utc::now(), "app".to_owned(), line);
+                    let log_item = LogItem::new(Utc::now(), source.clone(), line);
                     println!("{log_item}");
                 }
             }
@@ -1377,156 +2468,122 @@ impl Cyndra {
             }
         });
 
-        #[cfg(target_family = "unix")]
-        let exit_result = {
-            let mut sigterm_notif =
-                tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
-                    .expect("Can not get the SIGTERM signal receptor");
-            let mut sigint_notif =
-                tokio::signal::unix::signal(tokio::signal::unix::SignalKind::interrupt())
-                    .expect("Can not get the SIGINT signal receptor");
-            tokio::select! {
-                exit_result = runtime.wait() => {
-                    Some(exit_result)
-                }
-                _ = sigterm_notif.recv() => {
-                    eprintln!("Received SIGTERM. Killing the runtime...");
-                    None
-                },
-                _ = sigint_notif.recv() => {
-                    eprintln!("Received SIGINT. Killing the runtime...");
-                    None
-                }
-            }
-        };
-        #[cfg(target_family = "windows")]
-        let exit_result = {
-            let mut ctrl_break_notif = tokio::signal::windows::ctrl_break()
-                .expect("Can not get the CtrlBreak signal receptor");
-            let mut ctrl_c_notif =
-                tokio::signal::windows::ctrl_c().expect("Can not get the CtrlC signal receptor");
-            let mut ctrl_close_notif = tokio::signal::windows::ctrl_close()
-                .expect("Can not get the CtrlClose signal receptor");
-            let mut ctrl_logoff_notif = tokio::signal::windows::ctrl_logoff()
-                .expect("Can not get the CtrlLogoff signal receptor");
-            let mut ctrl_shutdown_notif = tokio::signal::windows::ctrl_shutdown()
-                .expect("Can not get the CtrlShutdown signal receptor");
-            tokio::select! {
-                exit_result = runtime.wait() => {
-                    Some(exit_result)
-                }
-                _ = ctrl_break_notif.recv() => {
-                    eprintln!("Received ctrl-break.");
-                    None
-                },
-                _ = ctrl_c_notif.recv() => {
-                    eprintln!("Received ctrl-c.");
-                    None
-                },
-                _ = ctrl_close_notif.recv() => {
-                    eprintln!("Received ctrl-close.");
-                    None
-                },
-                _ = ctrl_logoff_notif.recv() => {
-                    eprintln!("Received ctrl-logoff.");
-                    None
-                },
-                _ = ctrl_shutdown_notif.recv() => {
-                    eprintln!("Received ctrl-shutdown.");
-                    None
-                }
-            }
-        };
-        match exit_result {
-            Some(Ok(exit_status)) => {
-                bail!(
-                    "Runtime process exited with code {}",
-                    exit_status.code().unwrap_or_default()
-                );
-            }
-            Some(Err(e)) => {
-                bail!("Failed to wait for runtime process to exit: {e}");
-            }
-            None => {
-                runtime.kill().await?;
-            }
-        }
-
-        Ok(())
+        Ok(runtime)
     }
 
     async fn deploy(&mut self, args: DeployArgs) -> Result<()> {
         let client = self.client.as_ref().unwrap();
         let working_directory = self.ctx.working_directory();
-        let manifest_path = working_directory.join("Cargo.toml");
 
-        let secrets = Cyndra::get_secrets(&args.secret_args, working_directory, false)?;
+        let secrets = Cyndra::get_secrets(
+            &args.secret_args,
+            working_directory,
+            false,
+            self.ctx.secrets_config(),
+        )
+        .await?;
 
         // Image deployment mode
-        if let Some(image) = args.image {
+        let image = if args.local_build {
+            Some(self.build_and_push_image(&args).await?)
+        } else {
+            args.image.clone()
+        };
+        if let Some(image) = image {
             let pid = self.ctx.project_id();
             let deployment_req_image = DeploymentRequestImage { image, secrets };
 
+            let previous = self.rollback_target(pid, &args.health_args).await?;
             let deployment = client
                 .deploy(pid, DeploymentRequest::Image(deployment_req_image))
                 .await?;
 
             if args.tracking_args.no_follow {
-                println!("{}", deployment.to_string_colored());
-                return Ok(());
+                return self.print_deployment(&deployment);
             }
 
             return self
-                .track_deployment_status_and_print_logs_on_fail(
+                .track_and_check_deployment(
                     pid,
                     &deployment.id,
-                    args.tracking_args.raw,
+                    &args.tracking_args,
+                    &args.health_args,
+                    previous,
                 )
                 .await;
         }
 
-        // Build archive deployment mode
-        let mut deployment_req = DeploymentRequestBuildArchive {
-            secrets,
-            ..Default::default()
-        };
-        let mut build_meta = BuildMeta::default();
-        let mut rust_build_args = BuildArgsRust::default();
-
-        let metadata = async_cargo_metadata(manifest_path.as_path()).await?;
-        let packages = find_cyndra_packages(&metadata)?;
-        // TODO: support overriding this
-        let package = packages
-            .first()
-            .expect("Expected at least one crate with cyndra-runtime in the workspace");
-        let package_name = package.name.to_owned();
-        rust_build_args.package_name = Some(package_name);
-
-        // activate cyndra feature if present
-        let (no_default_features, features) = if package.features.contains_key("cyndra") {
-            (true, Some(vec!["cyndra".to_owned()]))
-        } else {
-            (false, None)
-        };
-        rust_build_args.no_default_features = no_default_features;
-        rust_build_args.features = features.map(|v| v.join(","));
+        // Git repository deployment mode
+        if let Some(repo_url) = args.git.clone() {
+            let git_ref = args
+                .git_ref
+                .as_deref()
+                .context("--ref is required with --git")?;
+            // git2 handles are not Send, so they must be dropped before the first await
+            let (commit_id, build_meta) = {
+                let repo = Repository::discover(working_directory)
+                    .context("a local clone of the repository is needed to check the ref")?;
+                let (commit, kind) =
+                    resolve_git_ref(&repo, &repo_url, git_ref, args.subdirectory.as_deref())
+                        .with_context(|| format!("validating ref '{git_ref}' of {repo_url}"))?;
+                let commit_id = commit.id().to_string();
+
+                let build_meta = BuildMeta {
+                    git_commit_id: Some(commit_id.clone()),
+                    git_commit_msg: commit
+                        .summary()
+                        .map(|s| s.chars().take(GIT_STRINGS_MAX_LENGTH).collect()),
+                    // the branch or tag name, unless a commit was given
+                    git_branch: (kind != GitRefKind::Commit)
+                        .then(|| git_ref.chars().take(GIT_STRINGS_MAX_LENGTH).collect()),
+                    git_dirty: Some(false),
+                };
+                (commit_id, build_meta)
+            };
+            eprintln!("Deploying commit {commit_id} from {repo_url}");
+
+            let deployment_req = DeploymentRequestGitRepo {
+                repo_url,
+                // pin the commit that was checked, in case a branch moves before the build
+                git_ref: commit_id,
+                subdirectory: args.subdirectory.clone(),
+                // resolved by the server from the checked out ref, since the local tree may differ
+                build_args: None,
+                secrets,
+                build_meta: Some(build_meta),
+            };
 
-        rust_build_args.cyndra_runtime_version = package
-            .dependencies
-            .iter()
-            .find(|dependency| dependency.name == RUNTIME_NAME)
-            .expect("cyndra package to have runtime dependency")
-            .req
-            .comparators
-            .first()
-            // is "^0.X.0" when `cyndra-runtime = "0.X.0"` is in Cargo.toml
-            .and_then(|c| c.to_string().strip_prefix('^').map(ToOwned::to_owned));
+            let pid = self.ctx.project_id();
+            let previous = self.rollback_target(pid, &args.health_args).await?;
+            eprintln!("Creating deployment...");
+            let deployment = client
+                .deploy(pid, DeploymentRequest::GitRepo(deployment_req))
+                .await?;
 
-        // TODO: determine which (one) binary to build
+            if args.tracking_args.no_follow {
+                return self.print_deployment(&deployment);
+            }
 
-        deployment_req.build_args = Some(BuildArgs::Rust(rust_build_args));
+            return self
+                .track_and_check_deployment(
+                    pid,
+                    &deployment.id,
+                    &args.tracking_args,
+                    &args.health_args,
+                    previous,
+                )
+                .await;
+        }
+
+        // Build archive deployment mode
+        let mut deployment_req = DeploymentRequestBuildArchive {
+            secrets,
+            ..Default::default()
+        };
+        let mut build_meta = BuildMeta::default();
 
-        // TODO: have all of the above be configurable in CLI and Cyndra.toml
+        let rust_build_args = self.rust_build_args(&args).await?;
+        deployment_req.build_args = Some(BuildArgs::Rust(rust_build_args));
 
         if let Ok(repo) = Repository::discover(working_directory) {
             let repo_path = repo
@@ -1560,11 +2617,12 @@ impl Cyndra {
         }
 
         eprintln!("Packing files...");
-        let archive = self.make_archive(args.secret_args.secrets.clone())?;
+        let mut archive = self.make_archive(args.secret_args.secrets.clone())?;
 
         if let Some(path) = args.output_archive {
             eprintln!("Writing archive to {}", path.display());
-            std::fs::write(path, archive).context("writing archive")?;
+            let mut file = File::create(path).context("creating archive file")?;
+            std::io::copy(&mut archive.file, &mut file).context("writing archive")?;
 
             return Ok(());
         }
@@ -1573,41 +2631,328 @@ impl Cyndra {
 
         let pid = self.ctx.project_id();
 
-        eprintln!("Uploading code...");
-        let arch = client.upload_archive(pid, archive).await?;
+        let Archive { file, size, sha256 } = archive;
+        eprintln!("Archive size: {}, SHA-256: {sha256}", format_size(size));
+        let existing = client
+            .get_archive_by_hash(pid, &sha256)
+            .await
+            .unwrap_or_else(|e| {
+                // only an optimization, so upload the archive instead of failing
+                eprintln!(
+                    "{}",
+                    format!("Could not check for an identical archive: {e:#}").yellow()
+                );
+                None
+            });
+        let arch = match existing {
+            Some(arch) => {
+                eprintln!("An identical archive was already uploaded, skipping upload");
+                arch
+            }
+            None => {
+                eprintln!("Uploading code...");
+                let progress_bar = create_progress_bar(size, "Uploading code");
+                let progress = progress_bar.clone();
+                let arch = client
+                    .upload_archive(
+                        pid,
+                        tokio::fs::File::from_std(file),
+                        size,
+                        &sha256,
+                        move |sent| progress.set_position(sent),
+                    )
+                    .await;
+                progress_bar.finish_and_clear();
+                arch?
+            }
+        };
         deployment_req.archive_version_id = arch.archive_version_id;
         deployment_req.build_meta = Some(build_meta);
 
+        let previous = self.rollback_target(pid, &args.health_args).await?;
         eprintln!("Creating deployment...");
         let deployment = client
             .deploy(pid, DeploymentRequest::BuildArchive(deployment_req))
             .await?;
 
         if args.tracking_args.no_follow {
-            println!("{}", deployment.to_string_colored());
-            return Ok(());
+            return self.print_deployment(&deployment);
         }
 
-        self.track_deployment_status_and_print_logs_on_fail(
+        self.track_and_check_deployment(
             pid,
             &deployment.id,
-            args.tracking_args.raw,
+            &args.tracking_args,
+            &args.health_args,
+            previous,
         )
         .await
     }
 
-    /// Returns true if the deployment failed
-    async fn track_deployment_status(&self, pid: &str, id: &str) -> Result<bool> {
+    /// Builds the selected package locally, packs it into an image and pushes it to the registry.
+    /// Returns the image reference by digest, so that the deployment gets exactly this image.
+    async fn build_and_push_image(&self, args: &DeployArgs) -> Result<String> {
+        if !cfg!(all(target_os = "linux", target_arch = "x86_64")) {
+            bail!("Local image builds are only supported on x86_64 Linux, since the binary built on this machine is copied into the image");
+        }
+        let registry = args
+            .registry
+            .as_deref()
+            .or(self.ctx.build_config().and_then(|b| b.registry.as_deref()))
+            .context("A registry to push the image to is needed. Use `--registry` or set `registry` in the build section of Cyndra.toml")?;
+        let working_directory = self.ctx.working_directory();
+        let rust_build_args = self.rust_build_args(args).await?;
+
+        let (tx, mut rx) = tokio::sync::mpsc::channel::<String>(256);
+        tokio::task::spawn(async move {
+            while let Some(line) = rx.recv().await {
+                eprintln!("{line}");
+            }
+        });
+        eprintln!(
+            "{} {}",
+            "    Building".bold().green(),
+            working_directory.display()
+        );
+        let executable_path = build_binary(working_directory, &rust_build_args, tx).await?;
+
+        let context_dir = tempfile::tempdir().context("creating image build directory")?;
+        let binary_name = rust_build_args
+            .binary_name
+            .as_deref()
+            .expect("rust_build_args to select a binary");
+        std::fs::copy(&executable_path, context_dir.path().join(binary_name))
+            .context("copying the binary to the image build directory")?;
+        std::fs::write(
+            context_dir.path().join("Dockerfile"),
+            docker::dockerfile(binary_name),
+        )
+        .context("writing Dockerfile")?;
+
+        let repository = docker::image_repository(registry, self.ctx.project_name());
+        let tag = format!("{repository}:{}", Utc::now().format("%Y%m%d%H%M%S"));
+        eprintln!("Building image {tag}...");
+        docker::run(
+            &["build", "--platform", "linux/amd64", "--tag", &tag, "."],
+            context_dir.path(),
+        )
+        .await?;
+        eprintln!("Pushing image {tag}...");
+        docker::run(&["push", &tag], context_dir.path()).await?;
+
+        let repo_digests = docker::output(&[
+            "image",
+            "inspect",
+            "--format",
+            "{{json .RepoDigests}}",
+            &tag,
+        ])
+        .await?;
+        let image = docker::find_repo_digest(&repo_digests, &repository)?
+            .with_context(|| format!("no digest was found for {tag} after pushing it"))?;
+        eprintln!("Deploying image {image}");
+
+        Ok(image)
+    }
+
+    /// Build args for the package and binary selected by the args or Cyndra.toml
+    async fn rust_build_args(&self, args: &DeployArgs) -> Result<BuildArgsRust> {
+        let manifest_path = self.ctx.working_directory().join("Cargo.toml");
+        let mut rust_build_args = BuildArgsRust::default();
+
+        let build_config = self.ctx.build_config();
+        let metadata = async_cargo_metadata(manifest_path.as_path()).await?;
+        let packages = find_cyndra_packages(&metadata)?;
+        let package = select_package(
+            &packages,
+            args.package
+                .as_deref()
+                .or(build_config.and_then(|b| b.package.as_deref())),
+        )?;
+        rust_build_args.package_name = Some(package.name.to_owned());
+        rust_build_args.binary_name = Some(select_binary(
+            package,
+            args.bin
+                .as_deref()
+                .or(build_config.and_then(|b| b.binary.as_deref())),
+        )?);
+
+        // use the features from Cyndra.toml, otherwise activate cyndra feature if present
+        let (no_default_features, features) = match build_config.and_then(|b| b.features.as_ref()) {
+            Some(features) => (false, Some(features.clone())),
+            None if package.features.contains_key("cyndra") => {
+                (true, Some(vec!["cyndra".to_owned()]))
+            }
+            None => (false, None),
+        };
+        rust_build_args.no_default_features = build_config
+            .and_then(|b| b.no_default_features)
+            .unwrap_or(no_default_features);
+        rust_build_args.features = features.filter(|v| !v.is_empty()).map(|v| v.join(","));
+
+        rust_build_args.cyndra_runtime_version = package
+            .dependencies
+            .iter()
+            .find(|dependency| dependency.name == RUNTIME_NAME)
+            .expect("cyndra package to have runtime dependency")
+            .req
+            .comparators
+            .first()
+            // is "^0.X.0" when `cyndra-runtime = "0.X.0"` is in Cargo.toml
+            .and_then(|c| c.to_string().strip_prefix('^').map(ToOwned::to_owned));
+
+        Ok(rust_build_args)
+    }
+
+    /// The running deployment to go back to if the health checks of a new one fail
+    async fn rollback_target(
+        &self,
+        pid: &str,
+        health_args: &HealthCheckArgs,
+    ) -> Result<Option<DeploymentResponse>> {
+        if !health_args.rollback_on_failure {
+            return Ok(None);
+        }
+        let client = self.client.as_ref().unwrap();
+        let current = client
+            .get_current_deployment(pid)
+            .await?
+            .filter(|d| d.state == DeploymentState::Running);
+        if current.is_none() {
+            eprintln!("No deployment is running, so there is nothing to roll back to if the health checks fail");
+        }
+
+        Ok(current)
+    }
+
+    /// Tracks a new deployment, then runs the health checks if requested.
+    /// Rolls back to `previous` if the deployment fails or its health checks fail.
+    async fn track_and_check_deployment(
+        &self,
+        pid: &str,
+        id: &str,
+        tracking_args: &DeploymentTrackingArgs,
+        health_args: &HealthCheckArgs,
+        previous: Option<DeploymentResponse>,
+    ) -> Result<()> {
+        let raw = tracking_args.raw;
+        let deployment = self.track_deployment_status(pid, id).await?;
+
+        let (error, reason) = if deployment.state == DeploymentState::Failed {
+            self.print_failed_deployment_logs(pid, id, raw).await?;
+            (anyhow!("Deployment failed"), "failed")
+        } else if health_args.wait_healthy {
+            match self.wait_for_healthy(&deployment, health_args).await {
+                Ok(()) => return self.print_tracked_deployment(&deployment),
+                Err(e) => (e, "failed its health checks"),
+            }
+        } else {
+            return self.print_tracked_deployment(&deployment);
+        };
+
+        let Some(previous) = previous else {
+            return Err(error);
+        };
+        eprintln!("{}", format!("{error:#}").red());
+        eprintln!("Rolling back to deployment {}...", previous.id);
+        let client = self.client.as_ref().unwrap();
+        let rollback = client.redeploy(pid, &previous.id).await?;
+        self.track_deployment_status_and_print_logs_on_fail(pid, &rollback.id, raw)
+            .await?;
+        bail!(
+            "Deployment {} {reason}, rolled back to deployment {}",
+            deployment.id,
+            previous.id
+        );
+    }
+
+    /// Probes the URIs of a running deployment until they all respond with the expected status
+    async fn wait_for_healthy(
+        &self,
+        deployment: &DeploymentResponse,
+        health_args: &HealthCheckArgs,
+    ) -> Result<()> {
+        if deployment.state != DeploymentState::Running {
+            bail!(
+                "Deployment {} is {}, not running",
+                deployment.id,
+                deployment.state
+            );
+        }
+        if deployment.uris.is_empty() {
+            if !health_args.allow_no_uris {
+                bail!(
+                    "Deployment {} has no URIs to probe. Pass `--allow-no-uris` to skip the health checks.",
+                    deployment.id
+                );
+            }
+            eprintln!("Deployment has no URIs to probe, skipping health checks");
+            return Ok(());
+        }
+
+        let expected = reqwest::StatusCode::from_u16(health_args.smoke_status)
+            .context("invalid expected status code")?;
+        let urls = deployment
+            .uris
+            .iter()
+            .map(|uri| health::probe_url(uri, &health_args.smoke))
+            .collect::<Vec<_>>();
+        let client = reqwest::Client::new();
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(health_args.smoke_timeout);
+
+        wait_with_spinner(HEALTH_CHECK_INTERVAL_MILLIS, |_, pb| {
+            let client = &client;
+            let urls = &urls;
+            async move {
+                let remaining = deadline.saturating_duration_since(tokio::time::Instant::now());
+                let timeout = remaining.min(HEALTH_CHECK_PROBE_TIMEOUT);
+                let mut failures = Vec::new();
+                for url in urls {
+                    if let Err(failure) = health::probe(client, url, expected, timeout).await {
+                        failures.push(failure);
+                    }
+                }
+
+                let result = if failures.is_empty() {
+                    Ok(())
+                } else if tokio::time::Instant::now() >= deadline {
+                    Err(anyhow!(
+                        "Health checks did not pass within {} seconds:\n{}",
+                        health_args.smoke_timeout,
+                        failures.join("\n")
+                    ))
+                } else {
+                    pb.set_message(format!(
+                        "Waiting for health checks to pass ({} of {} failing)",
+                        failures.len(),
+                        urls.len()
+                    ));
+                    return Ok(None);
+                };
+                let count = urls.len();
+
+                Ok(Some(move || {
+                    if result.is_ok() {
+                        eprintln!("Health checks passed for {count} URI(s)");
+                    }
+                    result
+                }))
+            }
+        })
+        .await?
+    }
+
+    /// Returns the deployment once it reached a final state
+    async fn track_deployment_status(&self, pid: &str, id: &str) -> Result<DeploymentResponse> {
         let client = self.client.as_ref().unwrap();
-        let failed = wait_with_spinner(2000, |_, pb| async move {
+        let deployment = wait_with_spinner(2000, |_, pb| async move {
             let deployment = client.get_deployment(pid, id).await?;
 
             let state = deployment.state.clone();
             pb.set_message(deployment.to_string_summary_colored());
-            let failed = state == DeploymentState::Failed;
             let cleanup = move || {
                 eprintln!("{}", deployment.to_string_colored());
-                failed
+                deployment
             };
             match state {
                 DeploymentState::Pending
@@ -1622,7 +2967,7 @@ impl Cyndra {
         })
         .await?;
 
-        Ok(failed)
+        Ok(deployment)
     }
 
     async fn track_deployment_status_and_print_logs_on_fail(
@@ -1630,18 +2975,49 @@ impl Cyndra {
         proj_id: &str,
         depl_id: &str,
         raw: bool,
+    ) -> Result<DeploymentResponse> {
+        let deployment = self.track_deployment_status(proj_id, depl_id).await?;
+        if deployment.state == DeploymentState::Failed {
+            self.print_failed_deployment_logs(proj_id, depl_id, raw)
+                .await?;
+            return Err(anyhow!("Deployment failed"));
+        }
+
+        Ok(deployment)
+    }
+
+    async fn print_failed_deployment_logs(
+        &self,
+        proj_id: &str,
+        depl_id: &str,
+        raw: bool,
     ) -> Result<()> {
         let client = self.client.as_ref().unwrap();
-        let failed = self.track_deployment_status(proj_id, depl_id).await?;
-        if failed {
-            for log in client.get_deployment_logs(proj_id, depl_id).await?.logs {
-                if raw {
-                    println!("{}", log.line);
-                } else {
-                    println!("{log}");
-                }
+        for log in client.get_deployment_logs(proj_id, depl_id).await?.logs {
+            let line = if raw { log.line } else { log.to_string() };
+            // keep stdout for the error object in JSON mode
+            match self.output_mode {
+                OutputMode::Normal => println!("{line}"),
+                OutputMode::Json => eprintln!("{line}"),
             }
-            return Err(anyhow!("Deployment failed"));
+        }
+
+        Ok(())
+    }
+
+    /// In JSON mode, prints a deployment that was already shown by the status tracking
+    fn print_tracked_deployment(&self, deployment: &DeploymentResponse) -> Result<()> {
+        if self.output_mode == OutputMode::Json {
+            print_json(deployment)?;
+        }
+
+        Ok(())
+    }
+
+    fn print_deployment(&self, deployment: &DeploymentResponse) -> Result<()> {
+        match self.output_mode {
+            OutputMode::Normal => println!("{}", deployment.to_string_colored()),
+            OutputMode::Json => print_json(deployment)?,
         }
 
         Ok(())
@@ -1652,7 +3028,12 @@ impl Cyndra {
         let name = self.ctx.project_name();
         let project = client.create_project(name).await?;
 
-        println!("Created project '{}' with id {}", project.name, project.id);
+        match self.output_mode {
+            OutputMode::Normal => {
+                println!("Created project '{}' with id {}", project.name, project.id)
+            }
+            OutputMode::Json => print_json(&project)?,
+        }
 
         Ok(())
     }
@@ -1669,7 +3050,45 @@ impl Cyndra {
             )
             .await?;
 
-        println!("Renamed project {} to '{}'", project.id, project.name);
+        match self.output_mode {
+            OutputMode::Normal => {
+                println!("Renamed project {} to '{}'", project.id, project.name)
+            }
+            OutputMode::Json => print_json(&project)?,
+        }
+
+        Ok(())
+    }
+
+    /// Move the project to a team, or out of its team if `team` is None
+    async fn project_transfer(&self, team: Option<String>) -> Result<()> {
+        let client = self.client.as_ref().unwrap();
+
+        let (req, target) = match team {
+            Some(team) => {
+                let team = self.find_team(Some(team)).await?;
+                (
+                    ProjectUpdateRequest {
+                        team_id: Some(team.id),
+                        ..Default::default()
+                    },
+                    format!("team '{}'", team.name),
+                )
+            }
+            None => (
+                ProjectUpdateRequest {
+                    remove_from_team: Some(true),
+                    ..Default::default()
+                },
+                "your personal projects".to_owned(),
+            ),
+        };
+        let project = client.update_project(self.ctx.project_id(), req).await?;
+
+        match self.output_mode {
+            OutputMode::Normal => println!("Moved project '{}' to {target}", project.name),
+            OutputMode::Json => print_json(&project)?,
+        }
 
         Ok(())
     }
@@ -1677,6 +3096,11 @@ impl Cyndra {
     async fn projects_list(&self, table_args: TableArgs) -> Result<()> {
         let client = self.client.as_ref().unwrap();
         let all_projects = client.get_projects_list().await?.projects;
+        if self.output_mode == OutputMode::Json {
+            return print_json(&ProjectListResponse {
+                projects: all_projects,
+            });
+        }
         // partition by team id and print separate tables
         let mut all_projects_map = BTreeMap::new();
         for proj in all_projects {
@@ -1704,7 +3128,10 @@ impl Cyndra {
     async fn project_status(&self) -> Result<()> {
         let client = self.client.as_ref().unwrap();
         let project = client.get_project(self.ctx.project_id()).await?;
-        print!("{}", project.to_string_colored());
+        match self.output_mode {
+            OutputMode::Normal => print!("{}", project.to_string_colored()),
+            OutputMode::Json => print_json(&project)?,
+        }
 
         Ok(())
     }
@@ -1745,12 +3172,17 @@ impl Cyndra {
 
         let res = client.delete_project(pid).await?;
 
-        println!("{res}");
+        print_message(self.output_mode, &res)?;
 
         Ok(())
     }
 
-    fn make_archive(&self, secrets_file: Option<PathBuf>) -> Result<Vec<u8>> {
+    /// The files to pack into the deployment archive, by their name in the archive,
+    /// with the reason each one is included
+    fn archive_files(
+        &self,
+        secrets_file: Option<PathBuf>,
+    ) -> Result<BTreeMap<String, (PathBuf, ArchiveReason)>> {
         let include_patterns = self.ctx.include();
 
         let working_directory = self.ctx.working_directory();
@@ -1762,13 +3194,13 @@ impl Cyndra {
         let mut entries = Vec::new();
 
         // Default excludes
-        let ignore_overrides = OverrideBuilder::new(working_directory)
-            .add("!.git/")
-            .context("adding override `!.git/`")?
-            .add("!target/")
-            .context("adding override `!target/`")?
-            .add(&format!("!{STORAGE_DIRNAME}/"))
-            .context(format!("adding override `!{STORAGE_DIRNAME}/`"))?
+        let mut ignore_overrides = OverrideBuilder::new(working_directory);
+        for pattern in default_archive_excludes() {
+            ignore_overrides
+                .add(&format!("!{pattern}"))
+                .context(format!("adding override `!{pattern}`"))?;
+        }
+        let ignore_overrides = ignore_overrides
             .build()
             .context("building archive override rules")?;
         for r in WalkBuilder::new(working_directory)
@@ -1776,22 +3208,29 @@ impl Cyndra {
             .overrides(ignore_overrides)
             .build()
         {
-            entries.push(r.context("list dir entry")?.into_path())
+            entries.push((
+                r.context("list dir entry")?.into_path(),
+                ArchiveReason::NotIgnored,
+            ))
         }
 
         let mut globs = GlobSetBuilder::new();
+        // reason for each glob in the set, by index
+        let mut glob_reasons = Vec::new();
 
         if let Some(secrets_file) = secrets_file.clone() {
-            entries.push(secrets_file);
+            entries.push((secrets_file, ArchiveReason::SecretsFile));
         } else {
             // Default: Include all Secrets.toml files
             globs.add(Glob::new("**/Secrets.toml").unwrap());
+            glob_reasons.push(ArchiveReason::SecretsFile);
         }
 
         // User provided includes
         if let Some(rules) = include_patterns {
             for r in rules {
                 globs.add(Glob::new(r.as_str()).context(format!("parsing glob pattern {:?}", r))?);
+                glob_reasons.push(ArchiveReason::IncludeGlob(r.clone()));
             }
         }
 
@@ -1799,16 +3238,17 @@ impl Cyndra {
         let globs = globs.build().context("glob glob")?;
         for entry in walkdir::WalkDir::new(working_directory) {
             let path = entry.context("list dir")?.into_path();
-            if globs.is_match(
+            let matches = globs.matches(
                 path.strip_prefix(working_directory)
                     .context("strip prefix of path")?,
-            ) {
-                entries.push(path);
+            );
+            if let Some(i) = matches.first() {
+                entries.push((path, glob_reasons[*i].clone()));
             }
         }
 
         let mut archive_files = BTreeMap::new();
-        for path in entries {
+        for (path, reason) in entries {
             // It's not possible to add a directory to an archive
             if path.is_dir() {
                 trace!("Skipping {:?}: is a directory", path);
@@ -1821,47 +3261,272 @@ impl Cyndra {
             }
 
             // zip file puts all files in root
-            let mut name = path
-                .strip_prefix(working_directory)
-                .context("strip prefix of path")?
-                .to_owned();
+            let mut name = archive_name(working_directory, &path)?;
 
             // if this is the custom secrets file, rename it to Secrets.toml
             if secrets_file.as_ref().is_some_and(|sf| sf == &path) {
-                name.pop();
-                name.push("Secrets.toml");
+                name = match name.rsplit_once('/') {
+                    Some((dir, _)) => format!("{dir}/Secrets.toml"),
+                    None => "Secrets.toml".to_owned(),
+                };
             }
 
-            archive_files.insert(path, name);
+            archive_files.insert(name, (path, reason));
+        }
+
+        Ok(archive_files)
+    }
+
+    /// Prints what would be in the deployment archive and why, without building or uploading it
+    fn deploy_dry_run(&self, secrets_file: Option<PathBuf>) -> Result<()> {
+        let working_directory = self.ctx.working_directory();
+        let archive_files = self.archive_files(secrets_file)?;
+
+        let mut entries = Vec::new();
+        for (name, (path, reason)) in &archive_files {
+            entries.push(ArchiveEntry {
+                name: name.clone(),
+                is_dir: false,
+                size: Some(path.metadata()?.len()),
+                reason: reason.clone(),
+            });
+        }
+        let included = archive_files
+            .into_values()
+            .map(|(path, _)| path)
+            .collect::<HashSet<_>>();
+        entries.extend(excluded_entries(
+            working_directory,
+            &included,
+            &default_archive_excludes(),
+        )?);
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+
+        if self.output_mode == OutputMode::Json {
+            return print_json(&entries);
+        }
+
+        println!("{}", format_tree(&entries));
+        let files = entries
+            .iter()
+            .filter(|e| e.reason.is_included())
+            .collect::<Vec<_>>();
+        let total = files.iter().filter_map(|e| e.size).sum();
+        println!(
+            "{} files, {} total before compression",
+            files.len(),
+            format_size(total)
+        );
+
+        for file in &files {
+            if file.size.is_some_and(|s| s > LARGE_FILE_WARNING_BYTES) {
+                eprintln!(
+                    "{}",
+                    format!(
+                        "WARNING: {} is large ({}), consider excluding it",
+                        file.name,
+                        format_size(file.size.unwrap_or_default())
+                    )
+                    .yellow()
+                );
+            }
+            if looks_like_secret(&file.name) {
+                eprintln!(
+                    "{}",
+                    format!(
+                        "WARNING: {} looks like it contains secrets, use Secrets.toml for those instead",
+                        file.name
+                    )
+                    .yellow()
+                );
+            }
         }
 
+        Ok(())
+    }
+
+    fn make_archive(&self, secrets_file: Option<PathBuf>) -> Result<Archive> {
+        let archive_files = self.archive_files(secrets_file)?;
+
         if archive_files.is_empty() {
             error!("No files included in upload. Aborting...");
             bail!("No files included in upload.");
         }
 
-        let bytes = {
+        let deploy_config = self.ctx.deploy_config();
+        let compression = match deploy_config
+            .and_then(|d| d.compression)
+            .unwrap_or_default()
+        {
+            ArchiveCompression::Deflate => CompressionMethod::Deflated,
+            ArchiveCompression::Zstd => CompressionMethod::Zstd,
+            ArchiveCompression::Stored => CompressionMethod::Stored,
+        };
+        let compression_level = deploy_config.and_then(|d| d.compression_level);
+        let max_size = deploy_config
+            .and_then(|d| d.max_archive_size_mib)
+            .unwrap_or(DEFAULT_MAX_ARCHIVE_SIZE_MIB)
+            * 1024
+            * 1024;
+
+        let mut total = 0;
+        for (path, _) in archive_files.values() {
+            total += path.metadata()?.len();
+        }
+        let progress_bar = create_progress_bar(total, "Packing files");
+
+        // The archive only depends on the file names and contents, so that it can be compared by hash.
+        // Entries are sorted by name, and timestamps and permissions are not taken from the files.
+        let file = {
             debug!("making zip archive");
-            let mut zip = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
-            for (path, name) in archive_files {
+            let file = tempfile::tempfile().context("creating archive file")?;
+            let mut zip = zip::ZipWriter::new(LimitedWriter::new(file, max_size));
+            for (name, (path, _)) in archive_files {
                 debug!("Packing {path:?}");
 
-                // windows things
-                let name = name.to_str().expect("valid filename").replace('\\', "/");
-                zip.start_file(name, FileOptions::<()>::default())?;
-
-                let mut b = Vec::new();
-                File::open(path)?.read_to_end(&mut b)?;
-                zip.write_all(&b)?;
+                let options = FileOptions::<()>::default()
+                    .compression_method(compression)
+                    .compression_level(compression_level)
+                    .last_modified_time(zip::DateTime::default())
+                    .unix_permissions(if is_executable(&path)? { 0o755 } else { 0o644 })
+                    .large_file(path.metadata()?.len() >= u32::MAX as u64);
+                zip.start_file(name.as_str(), options)
+                    .with_context(|| format!("packing {name}"))?;
+
+                let mut file = progress_bar.wrap_read(File::open(&path)?);
+                std::io::copy(&mut file, &mut zip).with_context(|| format!("packing {name}"))?;
             }
             let r = zip.finish().context("finish encoding zip archive")?;
 
             r.into_inner()
         };
-        debug!("Archive size: {} bytes", bytes.len());
+        progress_bar.finish_and_clear();
+        let archive = Archive::new(file)?;
+        debug!("Archive size: {} bytes", archive.size);
+
+        Ok(archive)
+    }
+}
+
+/// Patterns of directories that are never in the deployment archive
+fn default_archive_excludes() -> Vec<String> {
+    vec![
+        ".git/".to_owned(),
+        "target/".to_owned(),
+        format!("{STORAGE_DIRNAME}/"),
+    ]
+}
+
+/// Whether a file has any execute bit set, which is kept in archives so that scripts still run
+#[cfg(unix)]
+fn is_executable(path: &Path) -> Result<bool> {
+    use std::os::unix::fs::PermissionsExt;
+
+    Ok(path.metadata()?.permissions().mode() & 0o111 != 0)
+}
+#[cfg(not(unix))]
+fn is_executable(_path: &Path) -> Result<bool> {
+    Ok(false)
+}
+
+/// Whether a resource is a database that can be dumped and restored
+fn is_database(resource_type: &ResourceType) -> bool {
+    matches!(
+        resource_type,
+        ResourceType::DatabaseSharedPostgres
+            | ResourceType::DatabaseAwsRdsPostgres
+            | ResourceType::DatabaseAwsRdsMySql
+            | ResourceType::DatabaseAwsRdsMariaDB
+    )
+}
+
+/// Dump a remote database with the locally installed `pg_dump` or `mysqldump`
+async fn dump_remote_database(
+    info: &DatabaseInfo,
+    writer: &mut (impl AsyncWrite + Unpin),
+) -> Result<()> {
+    let (program, mut cmd) = match info.engine().as_str() {
+        "postgres" => {
+            let mut cmd = tokio::process::Command::new("pg_dump");
+            cmd.arg("--no-owner")
+                .args(["--host", &info.hostname()])
+                .args(["--port", &info.port()])
+                .args(["--username", &info.role_name()])
+                .arg(info.database_name())
+                .env("PGPASSWORD", info.role_password());
+            ("pg_dump", cmd)
+        }
+        _ => {
+            let mut cmd = tokio::process::Command::new("mysqldump");
+            cmd.args(["--host", &info.hostname()])
+                .args(["--port", &info.port()])
+                .args(["--user", &info.role_name()])
+                .arg(info.database_name())
+                .env("MYSQL_PWD", info.role_password());
+            ("mysqldump", cmd)
+        }
+    };
+
+    let mut child = cmd
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .with_context(|| format!("failed to run `{program}`. Is it installed and in PATH?"))?;
+    tokio::io::copy(&mut child.stdout.take().unwrap(), writer).await?;
+    writer.flush().await?;
+
+    let status = child.wait().await?;
+    if !status.success() {
+        bail!(
+            "`{program}` exited with code {}",
+            status.code().unwrap_or_default()
+        );
+    }
+
+    Ok(())
+}
 
-        Ok(bytes)
+/// Ask a runtime process to shut down gracefully, and kill it if it has not exited
+/// once its drain timeout has passed.
+async fn stop_runtime(package_name: String, mut runtime: tokio::process::Child) -> Result<()> {
+    // the runtime reads the same variable, since it inherits the environment
+    let shutdown_timeout = std::env::var("cyndra_SHUTDOWN_TIMEOUT")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .map(Duration::from_secs)
+        .unwrap_or(RUNTIME_SHUTDOWN_TIMEOUT);
+
+    // On Windows, the runtime gets the Ctrl-C of the console along with this process
+    #[cfg(target_family = "unix")]
+    if let Some(pid) = runtime.id() {
+        let pid = nix::unistd::Pid::from_raw(pid as i32);
+        if let Err(e) = nix::sys::signal::kill(pid, nix::sys::signal::Signal::SIGTERM) {
+            trace!("failed to send SIGTERM to the runtime of {package_name}: {e}");
+        }
+    }
+
+    match tokio::time::timeout(shutdown_timeout + RUNTIME_EXIT_GRACE, runtime.wait()).await {
+        Ok(status) => {
+            status.with_context(|| {
+                format!("failed to wait for runtime process of {package_name} to exit")
+            })?;
+        }
+        Err(_) => {
+            eprintln!("Runtime of {package_name} did not shut down in time. Killing it...");
+            runtime.kill().await?;
+        }
     }
+
+    Ok(())
+}
+
+/// Waits until one of the runtime processes exits, and returns which one it was.
+async fn wait_for_any_runtime(
+    runtimes: &mut [(String, tokio::process::Child)],
+) -> (String, std::io::Result<std::process::ExitStatus>) {
+    let (exit_result, index, _) =
+        futures::future::select_all(runtimes.iter_mut().map(|(_, r)| Box::pin(r.wait()))).await;
+
+    (runtimes[index].0.clone(), exit_result)
 }
 
 /// Calls async function `f` in a loop with `millis` sleep between iterations,
@@ -1891,6 +3556,20 @@ where
     Ok(cleanup())
 }
 
+fn create_progress_bar(len: u64, message: &'static str) -> ProgressBar {
+    let pb = indicatif::ProgressBar::new(len);
+    pb.set_style(
+        indicatif::ProgressStyle::with_template(
+            "{msg} [{bar:30.yellow}] {bytes}/{total_bytes} ({bytes_per_sec})",
+        )
+        .unwrap()
+        .progress_chars("=> "),
+    );
+    pb.set_message(message);
+
+    pb
+}
+
 fn create_spinner() -> ProgressBar {
     let pb = indicatif::ProgressBar::new_spinner();
     pb.enable_steady_tick(std::time::Duration::from_millis(250));
@@ -1921,8 +3600,7 @@ mod tests {
 
     use crate::args::{DeployArgs, ProjectArgs, SecretsArgs};
     use crate::Cyndra;
-    use std::fs::{self, canonicalize};
-    use std::io::Cursor;
+    use std::fs::{self, canonicalize, File};
     use std::path::PathBuf;
 
     pub fn path_from_workspace_root(path: &str) -> PathBuf {
@@ -1947,7 +3625,7 @@ mod tests {
             .make_archive(deploy_args.secret_args.secrets)
             .unwrap();
 
-        let mut zip = ZipArchive::new(Cursor::new(archive)).unwrap();
+        let mut zip = ZipArchive::new(archive.file).unwrap();
         (0..zip.len())
             .map(|i| zip.by_index(i).unwrap().name().to_owned())
             .collect()
@@ -2031,6 +3709,83 @@ mod tests {
         );
     }
 
+    /// Loads a project in a temporary directory with these files in it
+    async fn temp_project(files: &[(&str, &[u8])]) -> (tempfile::TempDir, Cyndra) {
+        let dir = tempfile::tempdir().unwrap();
+        let working_directory = canonicalize(dir.path()).unwrap();
+        for (name, content) in files {
+            let path = working_directory.join(name);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, content).unwrap();
+        }
+
+        let mut cyndra = Cyndra::new(crate::Binary::Cyndra, None).unwrap();
+        cyndra
+            .load_project(
+                &ProjectArgs {
+                    working_directory,
+                    name_or_id: Some("proj_archive-test".to_owned()),
+                },
+                false,
+                false,
+            )
+            .await
+            .unwrap();
+
+        (dir, cyndra)
+    }
+
+    #[tokio::test]
+    async fn make_archive_is_deterministic() {
+        let (_dir, cyndra) = temp_project(&[
+            ("Cargo.toml", b"[package]\nname = \"det\""),
+            ("src/main.rs", b"fn main() {}"),
+        ])
+        .await;
+        let first = cyndra.make_archive(None).unwrap().sha256;
+
+        let main = File::options()
+            .write(true)
+            .open(cyndra.ctx.working_directory().join("src/main.rs"))
+            .unwrap();
+        main.set_modified(
+            std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_000_000),
+        )
+        .unwrap();
+        drop(main);
+        let second = cyndra.make_archive(None).unwrap().sha256;
+
+        assert_eq!(first, second);
+    }
+
+    #[tokio::test]
+    async fn make_archive_respects_size_limit() {
+        let data = vec![0; 1024 * 1024];
+        let (_dir, cyndra) = temp_project(&[
+            ("Cargo.toml", b"[package]\nname = \"big\""),
+            (
+                "Cyndra.toml",
+                b"[deploy]\nmax_archive_size_mib = 1\ncompression = \"stored\"",
+            ),
+            ("data.bin", &data),
+        ])
+        .await;
+        let error = cyndra.make_archive(None).unwrap_err();
+        assert!(format!("{error:#}").contains("larger than the maximum of 1.0 MiB"));
+
+        // compresses well
+        let (_dir, cyndra) = temp_project(&[
+            ("Cargo.toml", b"[package]\nname = \"big\""),
+            (
+                "Cyndra.toml",
+                b"[deploy]\nmax_archive_size_mib = 1\ncompression = \"zstd\"",
+            ),
+            ("data.bin", &data),
+        ])
+        .await;
+        assert!(cyndra.make_archive(None).is_ok());
+    }
+
     #[tokio::test]
     async fn finds_workspace_root() {
         let project_args = ProjectArgs {
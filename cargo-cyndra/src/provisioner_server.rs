@@ -1,15 +1,25 @@
 use std::{
-    collections::HashMap, convert::Infallible, io::stderr, net::SocketAddr, process::exit,
-    sync::Arc, time::Duration,
+    collections::{BTreeSet, HashMap},
+    convert::Infallible,
+    io::stderr,
+    net::{Ipv4Addr, SocketAddr},
+    path::{Path, PathBuf},
+    process::exit,
+    sync::Arc,
+    time::{Duration, Instant},
 };
 
 use anyhow::{bail, Context, Result};
 use bollard::{
-    container::{Config, CreateContainerOptions, StartContainerOptions},
+    container::{
+        Config, CreateContainerOptions, ListContainersOptions, RemoveContainerOptions,
+        StartContainerOptions,
+    },
     exec::{CreateExecOptions, CreateExecResults},
     image::CreateImageOptions,
     models::{CreateImageInfo, HostConfig, PortBinding, ProgressDetail},
     service::ContainerInspectResponse,
+    volume::{CreateVolumeOptions, ListVolumesOptions, RemoveVolumeOptions},
     Docker,
 };
 use crossterm::{
@@ -18,26 +28,229 @@ use crossterm::{
     QueueableCommand,
 };
 use futures::StreamExt;
-use http_body_util::{combinators::BoxBody, BodyExt, Empty, Full};
+use http_body_util::{combinators::BoxBody, BodyExt, Full};
 use hyper::{
     body::{self, Bytes},
+    header::CONTENT_TYPE,
     server::conn::http1,
     service::service_fn,
-    Method, Request as HyperRequest, Response,
+    Method, Request as HyperRequest, Response, StatusCode,
 };
 use hyper_util::rt::TokioIo;
 use portpicker::pick_unused_port;
 use cyndra_common::{
-    models::resource::{
-        self, ProvisionResourceRequest, ResourceResponse, ResourceState, ResourceType,
+    models::{
+        error::ApiError,
+        resource::{self, ProvisionResourceRequest, ResourceResponse, ResourceState, ResourceType},
     },
     secrets::Secret,
     tables::get_resource_tables,
-    ContainerRequest, ContainerResponse, DatabaseInfo, DbInput,
+    ContainerReadyCheck, ContainerRequest, ContainerResponse, DatabaseInfo, DbInput,
+};
+use tokio::{
+    io::{AsyncWrite, AsyncWriteExt},
+    net::TcpListener,
+    time::sleep,
 };
-use tokio::{net::TcpListener, time::sleep};
 use tracing::{debug, error, trace};
 
+use crate::util::health::{probe_tcp, probe_url};
+
+/// Label put on the volumes of local resources to find them again when resetting a project
+const PROJECT_LABEL: &str = "dev.cyndra.project";
+/// How long to wait for a container to become ready if the request does not say
+const DEFAULT_READY_TIMEOUT: Duration = Duration::from_secs(60);
+/// Time between the ready checks of a container
+const READY_CHECK_INTERVAL: Duration = Duration::from_millis(500);
+/// How long one TCP or HTTP ready check may take
+const READY_PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Docker failures of the local provisioner, with what it was working on
+#[derive(Debug, thiserror::Error)]
+pub enum ProvisionerError {
+    #[error(
+        "failed to connect to Docker. Make sure that a Docker engine is running. \
+        For Podman or rootless Docker, set DOCKER_HOST to the socket of the engine. \
+        For more help: https://docs.cyndra.dev/docs/local-run#docker-engines"
+    )]
+    Connect(#[source] bollard::errors::Error),
+    #[error(
+        "failed to pull image '{image}'. \
+        If it is in a private registry, log in to it with `docker login`"
+    )]
+    PullImage {
+        image: String,
+        #[source]
+        source: bollard::errors::Error,
+    },
+    #[error("failed to inspect volume '{volume}'")]
+    InspectVolume {
+        volume: String,
+        #[source]
+        source: bollard::errors::Error,
+    },
+    #[error("failed to create volume '{volume}'")]
+    CreateVolume {
+        volume: String,
+        #[source]
+        source: bollard::errors::Error,
+    },
+    #[error("failed to create container '{container}' from image '{image}'")]
+    CreateContainer {
+        container: String,
+        image: String,
+        #[source]
+        source: bollard::errors::Error,
+    },
+    #[error("failed to inspect container '{container}'")]
+    InspectContainer {
+        container: String,
+        #[source]
+        source: bollard::errors::Error,
+    },
+    #[error(
+        "failed to start container '{container}'. If its host port {host_port} is used by \
+        something else, remove the container with `docker rm -f {container}` to get a new port"
+    )]
+    StartContainer {
+        container: String,
+        host_port: String,
+        #[source]
+        source: bollard::errors::Error,
+    },
+    #[error(
+        "container '{container}' does not publish port {port}. \
+        Remove it with `docker rm -f {container}` to create it again"
+    )]
+    MissingPortBinding { container: String, port: String },
+    #[error("Docker did not report whether container '{container}' is running")]
+    MissingState { container: String },
+    #[error("no free host port was found for port {port} of container '{container}'")]
+    NoFreePort { container: String, port: String },
+    #[error(
+        "container '{container}' stopped with exit code {exit_code} before it was ready. \
+        See its output with `docker logs {container}`"
+    )]
+    ContainerStopped { container: String, exit_code: i64 },
+    #[error(
+        "container '{container}' was not ready after {timeout_secs} seconds. \
+        The last {check} failed with: {reason}. \
+        See its output with `docker logs {container}`"
+    )]
+    NotReady {
+        container: String,
+        timeout_secs: u64,
+        check: ContainerReadyCheck,
+        reason: String,
+    },
+    #[error(
+        "failed to run command in container '{container}'. Has it been started by `cyndra run`?"
+    )]
+    Exec {
+        container: String,
+        #[source]
+        source: bollard::errors::Error,
+    },
+    #[error("failed to write the output of the command in container '{container}'")]
+    ExecOutput {
+        container: String,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("command in container '{container}' failed with exit code {exit_code}: {stderr}")]
+    ExecFailed {
+        container: String,
+        exit_code: i64,
+        stderr: String,
+    },
+    #[error("failed to list containers")]
+    ListContainers(#[source] bollard::errors::Error),
+    #[error("failed to remove container '{container}'")]
+    RemoveContainer {
+        container: String,
+        #[source]
+        source: bollard::errors::Error,
+    },
+    #[error("failed to list volumes")]
+    ListVolumes(#[source] bollard::errors::Error),
+    #[error("failed to remove volume '{volume}'")]
+    RemoveVolume {
+        volume: String,
+        #[source]
+        source: bollard::errors::Error,
+    },
+    #[error("'{resource_type}' resources can not be provisioned locally")]
+    UnsupportedResource { resource_type: ResourceType },
+    #[error("container '{container}' was not found. Has it been started by `cyndra run`?")]
+    MissingContainer { container: String },
+    #[error("extensions can only be created in Postgres databases, not in {engine}")]
+    ExtensionsNotSupported { engine: String },
+    #[error("init SQL files can only be run in SQL databases, not in {engine}")]
+    InitSqlNotSupported { engine: String },
+    #[error("failed to create extension '{extension}'")]
+    CreateExtension {
+        extension: String,
+        #[source]
+        source: Box<ProvisionerError>,
+    },
+    #[error("failed to read init SQL file '{file}'")]
+    ReadInitSql {
+        file: String,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error(
+        "failed to run init SQL file '{file}'. \
+        Run `cyndra resource reset` to start over with an empty database"
+    )]
+    RunInitSql {
+        file: String,
+        #[source]
+        source: Box<ProvisionerError>,
+    },
+    #[error(
+        "the environment of container '{container}' changed, \
+        but its database was already created with the old one. \
+        Run `cyndra resource reset` to start over with an empty database"
+    )]
+    EnvChanged { container: String },
+    #[error("invalid resource request")]
+    InvalidRequest(#[source] serde_json::Error),
+    #[error("unsupported request {method} {uri}")]
+    UnsupportedRequest { method: Method, uri: String },
+}
+
+impl ProvisionerError {
+    /// Report errors that mean that Docker could not be reached as such, and wrap others with `f`
+    fn from_docker(
+        error: bollard::errors::Error,
+        f: impl FnOnce(bollard::errors::Error) -> Self,
+    ) -> Self {
+        match error {
+            bollard::errors::Error::SocketNotFoundError(_)
+            | bollard::errors::Error::HyperLegacyError { .. }
+            | bollard::errors::Error::IOError { .. } => Self::Connect(error),
+            error => f(error),
+        }
+    }
+
+    /// Status that the provisioner API responds with for this error
+    fn status(&self) -> StatusCode {
+        match self {
+            Self::Connect(_) => StatusCode::SERVICE_UNAVAILABLE,
+            Self::UnsupportedResource { .. }
+            | Self::ExtensionsNotSupported { .. }
+            | Self::InitSqlNotSupported { .. }
+            | Self::ReadInitSql { .. }
+            | Self::InvalidRequest(_) => StatusCode::BAD_REQUEST,
+            Self::UnsupportedRequest { .. } => StatusCode::NOT_FOUND,
+            Self::EnvChanged { .. } => StatusCode::CONFLICT,
+            Self::NotReady { .. } => StatusCode::GATEWAY_TIMEOUT,
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
 /// A provisioner for local runs
 /// It uses Docker to create Databases
 pub struct LocalProvisioner {
@@ -45,36 +258,29 @@ pub struct LocalProvisioner {
 }
 
 impl LocalProvisioner {
-    pub fn new() -> Result<Self> {
+    pub fn new() -> Result<Self, ProvisionerError> {
         // This only constructs the client and does not try to connect.
         // If the socket is not found, a "no such file" error will happen on the first request to Docker.
         Ok(Self {
-            docker: Docker::connect_with_defaults()?,
+            docker: Docker::connect_with_defaults().map_err(ProvisionerError::Connect)?,
         })
     }
 
     fn get_container_first_host_port(
         &self,
         container: &ContainerInspectResponse,
+        container_name: &str,
         port: &str,
-    ) -> String {
+    ) -> Result<String, ProvisionerError> {
         container
             .host_config
             .as_ref()
-            .expect("container to have host config")
-            .port_bindings
-            .as_ref()
-            .expect("port bindings on container")
-            .get(port)
-            .expect("a port bindings entry")
-            .as_ref()
-            .expect("a port bindings")
-            .first()
-            .expect("at least one port binding")
-            .host_port
-            .as_ref()
-            .expect("a host port")
-            .clone()
+            .and_then(|c| c.port_bindings.as_ref())
+            .and_then(|bindings| bindings.get(port)?.as_ref()?.first()?.host_port.clone())
+            .ok_or_else(|| ProvisionerError::MissingPortBinding {
+                container: container_name.to_string(),
+                port: port.to_string(),
+            })
     }
 
     async fn start_container_if_not_running(
@@ -82,29 +288,48 @@ impl LocalProvisioner {
         container: &ContainerInspectResponse,
         container_type: &str,
         name: &str,
-    ) {
-        if !container
+        host_port: &str,
+    ) -> Result<(), ProvisionerError> {
+        let running = container
             .state
             .as_ref()
-            .expect("container to have a state")
-            .running
-            .expect("state to have a running key")
-        {
+            .and_then(|s| s.running)
+            .ok_or_else(|| ProvisionerError::MissingState {
+                container: name.to_string(),
+            })?;
+        if !running {
             trace!("{container_type} container '{name}' not running, so starting it");
             self.docker
                 .start_container(name, None::<StartContainerOptions<String>>)
                 .await
-                .expect("failed to start container");
+                .map_err(|e| {
+                    ProvisionerError::from_docker(e, |source| ProvisionerError::StartContainer {
+                        container: name.to_string(),
+                        host_port: host_port.to_string(),
+                        source,
+                    })
+                })?;
         }
+
+        Ok(())
     }
 
+    /// Inspect a container, creating it from `config` if it does not exist yet.
+    /// The container gets the project label and `port` published on a free host port.
     async fn get_container(
         &self,
+        project_name: &str,
         container_name: &str,
-        image: &str,
         port: &str,
-        env: Option<Vec<String>>,
-    ) -> Result<ContainerInspectResponse> {
+        mut config: Config<String>,
+    ) -> Result<ContainerInspectResponse, ProvisionerError> {
+        let inspect_error = |e| {
+            ProvisionerError::from_docker(e, |source| ProvisionerError::InspectContainer {
+                container: container_name.to_string(),
+                source,
+            })
+        };
+
         match self.docker.inspect_container(container_name, None).await {
             Ok(container) => {
                 trace!("found container {container_name}");
@@ -113,14 +338,18 @@ impl LocalProvisioner {
             Err(bollard::errors::Error::DockerResponseServerError {
                 status_code: 404, ..
             }) => {
-                self.pull_image(image).await.expect("failed to pull image");
+                let image = config.image.clone().unwrap_or_default();
+                self.pull_image(&image).await?;
                 trace!("will create container {container_name}");
                 let options = Some(CreateContainerOptions {
                     name: container_name,
                     platform: None,
                 });
                 let mut port_bindings = HashMap::new();
-                let host_port = pick_unused_port().expect("system to have a free port");
+                let host_port = pick_unused_port().ok_or_else(|| ProvisionerError::NoFreePort {
+                    container: container_name.to_string(),
+                    port: port.to_string(),
+                })?;
                 port_bindings.insert(
                     port.to_string(),
                     Some(vec![PortBinding {
@@ -128,89 +357,177 @@ impl LocalProvisioner {
                         ..Default::default()
                     }]),
                 );
-                let host_config = HostConfig {
-                    port_bindings: Some(port_bindings),
-                    ..Default::default()
-                };
-
-                let config: Config<String> = Config {
-                    image: Some(image.to_string()),
-                    env,
-                    host_config: Some(host_config),
-                    ..Default::default()
-                };
+                config
+                    .host_config
+                    .get_or_insert_with(Default::default)
+                    .port_bindings = Some(port_bindings);
+                config.labels = Some(HashMap::from([(
+                    PROJECT_LABEL.to_string(),
+                    project_name.to_string(),
+                )]));
 
                 self.docker
                     .create_container(options, config)
                     .await
-                    .expect("to be able to create container");
+                    .map_err(|e| {
+                        ProvisionerError::from_docker(e, |source| {
+                            ProvisionerError::CreateContainer {
+                                container: container_name.to_string(),
+                                image,
+                                source,
+                            }
+                        })
+                    })?;
 
-                Ok(self
-                    .docker
+                self.docker
                     .inspect_container(container_name, None)
                     .await
-                    .expect("container to be created"))
-            }
-            Err(error) => {
-                error!("Got unexpected error while inspecting docker container: {error}");
-                error!(
-                    "Make sure Docker is installed and running. For more help: https://docs.cyndra.dev/docs/local-run#docker-engines"
-                );
-                Err(anyhow::anyhow!("{}", error))
+                    .map_err(inspect_error)
             }
+            Err(error) => Err(inspect_error(error)),
         }
     }
 
     async fn get_db_connection_string(
         &self,
         project_name: &str,
+        working_directory: &Path,
         db_type: ResourceType,
-        db_name: Option<String>,
-    ) -> Result<DatabaseInfo> {
+        input: DbInput,
+    ) -> Result<DatabaseInfo, ProvisionerError> {
         trace!("getting sql string for project '{project_name}'");
 
-        let database_name = match db_type {
-            ResourceType::DatabaseAwsRdsPostgres
-            | ResourceType::DatabaseAwsRdsMySql
-            | ResourceType::DatabaseAwsRdsMariaDB => {
-                db_name.unwrap_or_else(|| project_name.to_string())
-            }
-            _ => project_name.to_string(),
-        };
+        let database_name = local_database_name(project_name, &db_type, input.db_name.clone());
 
-        let EngineConfig {
-            r#type,
-            image,
-            engine,
-            username,
-            password,
-            port,
-            env,
-            is_ready_cmd,
-        } = db_type_to_config(db_type, &database_name);
-        let container_name = format!("cyndra_{project_name}_{type}");
+        let mut config = db_type_to_config(db_type, &database_name)?;
+        let default_image = config.image.clone();
+        if let Some(image) = input.local_image {
+            config.image = image;
+        }
+        config.apply_env(&input.local_env);
+        if !input.local_extensions.is_empty() && config.engine != "postgres" {
+            return Err(ProvisionerError::ExtensionsNotSupported {
+                engine: config.engine,
+            });
+        }
+        if !input.local_init_sql.is_empty() && config.engine == "redis" {
+            return Err(ProvisionerError::InitSqlNotSupported {
+                engine: config.engine,
+            });
+        }
+        let container_name = format!("cyndra_{project_name}_{}", config.r#type);
+
+        // keep the data in a named volume so that it outlives the container.
+        // Databases can not always read the files of other versions, so every image gets its own.
+        let volume_name = if config.image == default_image {
+            format!("{container_name}_data")
+        } else {
+            format!(
+                "{container_name}_{}_data",
+                image_volume_suffix(&config.image)
+            )
+        };
+        let volume_created = self.create_volume(project_name, &volume_name).await?;
 
+        self.remove_container_if_changed(
+            &container_name,
+            &config.image,
+            config.env.as_deref().unwrap_or_default(),
+            volume_created || !config.env_initializes_data(),
+        )
+        .await?;
         let container = self
-            .get_container(&container_name, &image, &port, env)
+            .get_container(
+                project_name,
+                &container_name,
+                &config.port,
+                Config {
+                    image: Some(config.image.clone()),
+                    env: config.env.clone(),
+                    cmd: config.cmd(),
+                    host_config: Some(HostConfig {
+                        binds: Some(vec![format!("{volume_name}:{}", config.data_dir)]),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                },
+            )
             .await?;
 
-        let host_port = self.get_container_first_host_port(&container, &port);
+        let host_port =
+            self.get_container_first_host_port(&container, &container_name, &config.port)?;
 
-        self.start_container_if_not_running(&container, &r#type, &container_name)
-            .await;
+        self.start_container_if_not_running(
+            &container,
+            &config.r#type,
+            &container_name,
+            &host_port,
+        )
+        .await?;
 
-        self.wait_for_ready(&container_name, is_ready_cmd.clone())
-            .await?;
+        let ready_check = ContainerReadyCheck::Command(config.is_ready_cmd());
+        self.wait_for_ready(
+            &container_name,
+            &host_port,
+            &ready_check,
+            DEFAULT_READY_TIMEOUT,
+        )
+        .await?;
 
         // The container enters the ready state, runs an init script and then reboots, so we sleep
         // a little and then check if it's ready again afterwards.
         sleep(Duration::from_millis(450)).await;
-        self.wait_for_ready(&container_name, is_ready_cmd).await?;
+        self.wait_for_ready(
+            &container_name,
+            &host_port,
+            &ready_check,
+            DEFAULT_READY_TIMEOUT,
+        )
+        .await?;
+
+        // creating extensions is idempotent, so that extensions added later are created as well
+        for extension in &input.local_extensions {
+            trace!("creating extension {extension} in '{container_name}'");
+            self.exec(
+                &container_name,
+                config.restore_cmd(&database_name),
+                Some(format!("CREATE EXTENSION IF NOT EXISTS \"{extension}\";").into_bytes()),
+                None::<&mut tokio::io::Sink>,
+            )
+            .await
+            .map_err(|e| ProvisionerError::CreateExtension {
+                extension: extension.clone(),
+                source: Box::new(e),
+            })?;
+        }
+
+        if volume_created {
+            for file in &input.local_init_sql {
+                trace!("running {file} in '{container_name}'");
+                let sql = std::fs::read(working_directory.join(file)).map_err(|source| {
+                    ProvisionerError::ReadInitSql {
+                        file: file.clone(),
+                        source,
+                    }
+                })?;
+                self.exec(
+                    &container_name,
+                    config.restore_cmd(&database_name),
+                    Some(sql),
+                    None::<&mut tokio::io::Sink>,
+                )
+                .await
+                .map_err(|e| ProvisionerError::RunInitSql {
+                    file: file.clone(),
+                    source: Box::new(e),
+                })?;
+            }
+        }
 
         let res = DatabaseInfo::new(
-            engine,
-            username,
-            password.expose().clone(),
+            config.engine,
+            config.username,
+            config.password.expose().clone(),
             database_name,
             host_port,
             "localhost".to_string(),
@@ -220,69 +537,495 @@ impl LocalProvisioner {
         Ok(res)
     }
 
-    pub async fn start_container(&self, req: ContainerRequest) -> Result<ContainerResponse> {
+    /// Remove a database container that runs another image than `image`, has another env than `env`,
+    /// or was created without the project label, so that it gets created again.
+    /// An env that is only used when a database is initialized can not change once there is data,
+    /// so `env_changeable` tells whether the data is new or does not depend on the env.
+    async fn remove_container_if_changed(
+        &self,
+        container_name: &str,
+        image: &str,
+        env: &[String],
+        env_changeable: bool,
+    ) -> Result<(), ProvisionerError> {
+        // other errors are reported when getting the container
+        let Ok(container) = self.docker.inspect_container(container_name, None).await else {
+            return Ok(());
+        };
+        let config = container.config.unwrap_or_default();
+        let current = config.image.unwrap_or_default();
+        let labeled = config
+            .labels
+            .is_some_and(|labels| labels.contains_key(PROJECT_LABEL));
+        if !labeled {
+            // containers of older versions kept their data inside the container instead of a volume
+            println!(
+                "Recreating container '{container_name}' to keep its data in a volume. Its current data is not kept."
+            );
+        } else if current != image {
+            println!(
+                "Recreating container '{container_name}' to change its image from {current} to {image}"
+            );
+        } else {
+            // the container env also has the variables of the image, so leave those out
+            let Ok(image_env) = self
+                .docker
+                .inspect_image(image)
+                .await
+                .map(|i| i.config.and_then(|c| c.env).unwrap_or_default())
+            else {
+                return Ok(());
+            };
+            if !env_changed(&config.env.unwrap_or_default(), env, &image_env) {
+                return Ok(());
+            }
+            if !env_changeable {
+                return Err(ProvisionerError::EnvChanged {
+                    container: container_name.to_string(),
+                });
+            }
+            println!("Recreating container '{container_name}' to change its environment");
+        }
+
+        self.remove_container(container_name).await
+    }
+
+    /// Stop and remove a container
+    async fn remove_container(&self, container_name: &str) -> Result<(), ProvisionerError> {
+        self.docker
+            .remove_container(
+                container_name,
+                Some(RemoveContainerOptions {
+                    force: true,
+                    ..Default::default()
+                }),
+            )
+            .await
+            .map_err(|e| {
+                ProvisionerError::from_docker(e, |source| ProvisionerError::RemoveContainer {
+                    container: container_name.to_string(),
+                    source,
+                })
+            })
+    }
+
+    pub async fn start_container(
+        &self,
+        req: ContainerRequest,
+    ) -> Result<ContainerResponse, ProvisionerError> {
         let ContainerRequest {
             project_name,
             container_name,
             env,
             image,
             port,
+            ready_check,
+            ready_timeout_secs,
         } = req;
 
         let container_name = format!("cyndra_{project_name}_{container_name}");
 
         let container = self
-            .get_container(&container_name, &image, &port, Some(env))
+            .get_container(
+                &project_name,
+                &container_name,
+                &port,
+                Config {
+                    image: Some(image),
+                    env: Some(env),
+                    ..Default::default()
+                },
+            )
             .await?;
 
-        let host_port = self.get_container_first_host_port(&container, &port);
+        let host_port = self.get_container_first_host_port(&container, &container_name, &port)?;
 
-        self.start_container_if_not_running(&container, &container_name, &container_name)
-            .await;
+        self.start_container_if_not_running(
+            &container,
+            &container_name,
+            &container_name,
+            &host_port,
+        )
+        .await?;
+
+        if let Some(ready_check) = ready_check {
+            let timeout = ready_timeout_secs
+                .map(Duration::from_secs)
+                .unwrap_or(DEFAULT_READY_TIMEOUT);
+            self.wait_for_ready(&container_name, &host_port, &ready_check, timeout)
+                .await?;
+        }
 
         Ok(ContainerResponse { host_port })
     }
 
-    async fn wait_for_ready(&self, container_name: &str, is_ready_cmd: Vec<String>) -> Result<()> {
-        loop {
-            trace!("waiting for '{container_name}' to be ready for connections");
+    /// Create a volume if it does not exist yet, returning whether it was created
+    async fn create_volume(
+        &self,
+        project_name: &str,
+        volume_name: &str,
+    ) -> Result<bool, ProvisionerError> {
+        match self.docker.inspect_volume(volume_name).await {
+            Ok(_) => return Ok(false),
+            Err(bollard::errors::Error::DockerResponseServerError {
+                status_code: 404, ..
+            }) => {}
+            Err(e) => {
+                return Err(ProvisionerError::from_docker(e, |source| {
+                    ProvisionerError::InspectVolume {
+                        volume: volume_name.to_string(),
+                        source,
+                    }
+                }))
+            }
+        }
 
-            let config = CreateExecOptions {
-                cmd: Some(is_ready_cmd.clone()),
-                attach_stdout: Some(true),
-                attach_stderr: Some(true),
+        trace!("creating volume {volume_name}");
+        self.docker
+            .create_volume(CreateVolumeOptions {
+                name: volume_name,
+                labels: HashMap::from([(PROJECT_LABEL, project_name)]),
                 ..Default::default()
-            };
+            })
+            .await
+            .map_err(|e| {
+                ProvisionerError::from_docker(e, |source| ProvisionerError::CreateVolume {
+                    volume: volume_name.to_string(),
+                    source,
+                })
+            })?;
 
-            let CreateExecResults { id } = self
-                .docker
-                .create_exec(container_name, config)
-                .await
-                .expect("failed to create exec to check if container is ready");
+        Ok(true)
+    }
 
-            let ready_result = self
-                .docker
-                .start_exec(&id, None)
+    /// Stop and remove the local containers and volumes of a project.
+    /// Returns the names of what was removed.
+    pub async fn reset(&self, project_name: &str) -> Result<Vec<String>, ProvisionerError> {
+        let mut removed = Vec::new();
+
+        let label = format!("{PROJECT_LABEL}={project_name}");
+        let containers = self
+            .docker
+            .list_containers(Some(ListContainersOptions {
+                all: true,
+                filters: HashMap::from([("label", vec![label.as_str()])]),
+                ..Default::default()
+            }))
+            .await
+            .map_err(|e| ProvisionerError::from_docker(e, ProvisionerError::ListContainers))?;
+        for name in containers
+            .into_iter()
+            .filter_map(|c| c.names)
+            .flatten()
+            .filter_map(|n| n.strip_prefix('/').map(ToOwned::to_owned))
+        {
+            trace!("removing container {name}");
+            self.remove_container(&name).await?;
+            removed.push(name);
+        }
+        // older versions created the database containers without the label
+        for name in db_container_names(project_name) {
+            let exists = self.docker.inspect_container(&name, None).await.is_ok();
+            if !exists || removed.contains(&name) {
+                continue;
+            }
+            trace!("removing unlabeled container {name}");
+            self.remove_container(&name).await?;
+            removed.push(name);
+        }
+
+        let volumes = self
+            .docker
+            .list_volumes(Some(ListVolumesOptions {
+                filters: HashMap::from([("label", vec![label.as_str()])]),
+            }))
+            .await
+            .map_err(|e| ProvisionerError::from_docker(e, ProvisionerError::ListVolumes))?;
+        for name in volumes
+            .volumes
+            .unwrap_or_default()
+            .into_iter()
+            .map(|v| v.name)
+        {
+            trace!("removing volume {name}");
+            self.docker
+                .remove_volume(&name, None::<RemoveVolumeOptions>)
                 .await
-                .expect("failed to execute ready command");
+                .map_err(|e| {
+                    ProvisionerError::from_docker(e, |source| ProvisionerError::RemoveVolume {
+                        volume: name.clone(),
+                        source,
+                    })
+                })?;
+            removed.push(name);
+        }
+
+        Ok(removed)
+    }
+
+    /// Write an SQL dump of a project's local database to `writer`
+    pub async fn dump_database(
+        &self,
+        project_name: &str,
+        db_type: ResourceType,
+        db_name: Option<String>,
+        writer: &mut (impl AsyncWrite + Unpin),
+    ) -> Result<(), ProvisionerError> {
+        let database_name = local_database_name(project_name, &db_type, db_name);
+        let (config, container_name) = self.local_db_config(project_name, db_type).await?;
+
+        self.exec(
+            &container_name,
+            config.dump_cmd(&database_name),
+            None,
+            Some(writer),
+        )
+        .await
+    }
+
+    /// Run an SQL dump against a project's local database
+    pub async fn restore_database(
+        &self,
+        project_name: &str,
+        db_type: ResourceType,
+        db_name: Option<String>,
+        dump: Vec<u8>,
+    ) -> Result<(), ProvisionerError> {
+        let database_name = local_database_name(project_name, &db_type, db_name);
+        let (config, container_name) = self.local_db_config(project_name, db_type).await?;
+
+        self.exec(
+            &container_name,
+            config.restore_cmd(&database_name),
+            Some(dump),
+            None::<&mut tokio::io::Sink>,
+        )
+        .await
+    }
+
+    /// Config and container name of a project's local database,
+    /// with the credentials that its container was created with
+    async fn local_db_config(
+        &self,
+        project_name: &str,
+        db_type: ResourceType,
+    ) -> Result<(EngineConfig, String), ProvisionerError> {
+        let mut config = db_type_to_config(db_type, project_name)?;
+        let container_name = format!("cyndra_{project_name}_{}", config.r#type);
+
+        let env = self
+            .docker
+            .inspect_container(&container_name, None)
+            .await
+            .map_err(|e| match e {
+                bollard::errors::Error::DockerResponseServerError {
+                    status_code: 404, ..
+                } => ProvisionerError::MissingContainer {
+                    container: container_name.clone(),
+                },
+                e => {
+                    ProvisionerError::from_docker(e, |source| ProvisionerError::InspectContainer {
+                        container: container_name.clone(),
+                        source,
+                    })
+                }
+            })?
+            .config
+            .and_then(|c| c.env)
+            .unwrap_or_default();
+        config.apply_env(&env);
+
+        Ok((config, container_name))
+    }
+
+    /// Run a command in a container, optionally feeding it `stdin` and copying its stdout to `stdout`.
+    /// Fails with the command's stderr if it exits with a non-zero code.
+    async fn exec(
+        &self,
+        container_name: &str,
+        cmd: Vec<String>,
+        stdin: Option<Vec<u8>>,
+        mut stdout: Option<&mut (impl AsyncWrite + Unpin)>,
+    ) -> Result<(), ProvisionerError> {
+        let exec_error = |e| {
+            ProvisionerError::from_docker(e, |source| ProvisionerError::Exec {
+                container: container_name.to_string(),
+                source,
+            })
+        };
+        let output_error = |source| ProvisionerError::ExecOutput {
+            container: container_name.to_string(),
+            source,
+        };
+        let config = CreateExecOptions {
+            cmd: Some(cmd),
+            attach_stdin: Some(stdin.is_some()),
+            attach_stdout: Some(true),
+            attach_stderr: Some(true),
+            ..Default::default()
+        };
+
+        let CreateExecResults { id } = self
+            .docker
+            .create_exec(container_name, config)
+            .await
+            .map_err(exec_error)?;
 
-            if let bollard::exec::StartExecResults::Attached { mut output, .. } = ready_result {
-                while let Some(line) = output.next().await {
-                    trace!("line: {:?}", line);
+        // always attached, since the exec is not started detached
+        let bollard::exec::StartExecResults::Attached {
+            mut output,
+            mut input,
+        } = self
+            .docker
+            .start_exec(&id, None)
+            .await
+            .map_err(exec_error)?
+        else {
+            unreachable!("exec was started detached");
+        };
+
+        if let Some(stdin) = stdin {
+            // write in the background so that the output is read at the same time
+            tokio::spawn(async move {
+                if let Err(e) = input.write_all(&stdin).await {
+                    error!("failed to write to container command: {e}");
+                }
+                let _ = input.shutdown().await;
+            });
+        }
 
-                    if let bollard::container::LogOutput::StdOut { .. } =
-                        line.expect("output to have a log line")
-                    {
-                        return Ok(());
+        let mut stderr = Vec::new();
+        while let Some(line) = output.next().await {
+            match line.map_err(exec_error)? {
+                bollard::container::LogOutput::StdOut { message } => {
+                    if let Some(stdout) = stdout.as_mut() {
+                        stdout.write_all(&message).await.map_err(output_error)?;
                     }
                 }
+                bollard::container::LogOutput::StdErr { message } => {
+                    stderr.extend_from_slice(&message)
+                }
+                _ => {}
+            }
+        }
+        if let Some(stdout) = stdout {
+            stdout.flush().await.map_err(output_error)?;
+        }
+
+        let exit_code = self
+            .docker
+            .inspect_exec(&id)
+            .await
+            .map_err(exec_error)?
+            .exit_code;
+        if exit_code != Some(0) {
+            return Err(ProvisionerError::ExecFailed {
+                container: container_name.to_string(),
+                exit_code: exit_code.unwrap_or_default(),
+                stderr: String::from_utf8_lossy(&stderr).trim().to_string(),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Run `ready_check` until it passes, failing if it does not pass within `timeout`
+    /// or if the container stops
+    async fn wait_for_ready(
+        &self,
+        container_name: &str,
+        host_port: &str,
+        ready_check: &ContainerReadyCheck,
+        timeout: Duration,
+    ) -> Result<(), ProvisionerError> {
+        let started = Instant::now();
+        loop {
+            trace!("waiting for '{container_name}' to be ready for connections");
+
+            let state = self
+                .docker
+                .inspect_container(container_name, None)
+                .await
+                .map_err(|e| {
+                    ProvisionerError::from_docker(e, |source| ProvisionerError::InspectContainer {
+                        container: container_name.to_string(),
+                        source,
+                    })
+                })?
+                .state;
+            if let Some(state) = state.filter(|s| s.running == Some(false)) {
+                return Err(ProvisionerError::ContainerStopped {
+                    container: container_name.to_string(),
+                    exit_code: state.exit_code.unwrap_or_default(),
+                });
+            }
+
+            let error = match self
+                .check_ready(container_name, host_port, ready_check)
+                .await
+            {
+                Ok(()) => return Ok(()),
+                Err(error) => error,
+            };
+            trace!("'{container_name}' is not ready yet: {error:#}");
+
+            if started.elapsed() >= timeout {
+                return Err(ProvisionerError::NotReady {
+                    container: container_name.to_string(),
+                    timeout_secs: timeout.as_secs(),
+                    check: ready_check.clone(),
+                    reason: format!("{error:#}"),
+                });
             }
 
-            sleep(Duration::from_millis(500)).await;
+            sleep(READY_CHECK_INTERVAL).await;
         }
     }
 
-    async fn pull_image(&self, image: &str) -> Result<(), String> {
+    async fn check_ready(
+        &self,
+        container_name: &str,
+        host_port: &str,
+        ready_check: &ContainerReadyCheck,
+    ) -> Result<()> {
+        match ready_check {
+            ContainerReadyCheck::Command(cmd) => Ok(self
+                .exec(
+                    container_name,
+                    cmd.clone(),
+                    None,
+                    None::<&mut tokio::io::Sink>,
+                )
+                .await?),
+            ContainerReadyCheck::Tcp => {
+                let port = host_port
+                    .parse()
+                    .with_context(|| format!("invalid host port '{host_port}'"))?;
+                probe_tcp(
+                    SocketAddr::from((Ipv4Addr::LOCALHOST, port)),
+                    READY_PROBE_TIMEOUT,
+                )
+                .await
+                .map_err(anyhow::Error::msg)
+            }
+            ContainerReadyCheck::Http { path } => {
+                let url = probe_url(&format!("http://localhost:{host_port}"), path);
+                let res = reqwest::Client::new()
+                    .get(&url)
+                    .timeout(READY_PROBE_TIMEOUT)
+                    .send()
+                    .await
+                    .with_context(|| format!("{url} could not be reached"))?;
+                if !res.status().is_success() {
+                    bail!("{url} responded with {}", res.status());
+                }
+
+                Ok(())
+            }
+        }
+    }
+
+    async fn pull_image(&self, image: &str) -> Result<(), ProvisionerError> {
         trace!("pulling latest image for '{image}'");
         let mut layers = Vec::new();
 
@@ -293,7 +1036,12 @@ impl LocalProvisioner {
         let mut output = self.docker.create_image(create_image_options, None, None);
 
         while let Some(line) = output.next().await {
-            let info = line.expect("failed to create image");
+            let info = line.map_err(|e| {
+                ProvisionerError::from_docker(e, |source| ProvisionerError::PullImage {
+                    image: image.to_string(),
+                    source,
+                })
+            })?;
 
             if let Some(id) = info.id.as_ref() {
                 match layers
@@ -307,25 +1055,28 @@ impl LocalProvisioner {
                 layers.push(info);
             }
 
-            print_layers(&layers);
+            // the progress is only for show, so failing to print it is not an error
+            if let Err(e) = print_layers(&layers) {
+                trace!("failed to print image pull progress: {e}");
+            }
         }
 
         // Undo last MoveUps
-        stderr()
-            .queue(MoveDown(
-                layers.len().try_into().expect("to convert usize to u16"),
-            ))
-            .expect("to reset cursor position");
+        if let Err(e) = stderr().queue(MoveDown(layers_height(&layers))) {
+            trace!("failed to reset cursor position: {e}");
+        }
 
         Ok(())
     }
 }
 
-fn print_layers(layers: &Vec<CreateImageInfo>) {
+fn layers_height(layers: &[CreateImageInfo]) -> u16 {
+    layers.len().try_into().unwrap_or(u16::MAX)
+}
+
+fn print_layers(layers: &[CreateImageInfo]) -> std::io::Result<()> {
     for info in layers {
-        stderr()
-            .queue(Clear(ClearType::CurrentLine))
-            .expect("to be able to clear line");
+        stderr().queue(Clear(ClearType::CurrentLine))?;
 
         if let Some(id) = info.id.as_ref() {
             let text = match (info.status.as_deref(), info.progress_detail.as_ref()) {
@@ -346,17 +1097,12 @@ fn print_layers(layers: &Vec<CreateImageInfo>) {
             };
             println!("[{id} {text}]");
         } else {
-            println!(
-                "{}",
-                info.status.as_ref().expect("image info to have a status")
-            )
+            println!("{}", info.status.as_deref().unwrap_or_default())
         }
     }
-    stderr()
-        .queue(MoveUp(
-            layers.len().try_into().expect("to convert usize to u16"),
-        ))
-        .expect("to reset cursor position");
+    stderr().queue(MoveUp(layers_height(layers)))?;
+
+    Ok(())
 }
 
 struct EngineConfig {
@@ -367,11 +1113,185 @@ struct EngineConfig {
     password: Secret<String>,
     port: String,
     env: Option<Vec<String>>,
-    is_ready_cmd: Vec<String>,
+    /// Variables in `env` that set the username and password
+    username_env: Option<String>,
+    password_env: Option<String>,
+    /// Where the database keeps its data in the container
+    data_dir: String,
 }
 
-fn db_type_to_config(db_type: ResourceType, database_name: &str) -> EngineConfig {
+impl EngineConfig {
+    /// Overrides the command of the image
+    fn cmd(&self) -> Option<Vec<String>> {
+        match self.engine.as_str() {
+            // the image has no variable for the password, and only persists data when asked to
+            "redis" => Some(vec![
+                "redis-server".to_string(),
+                "--requirepass".to_string(),
+                self.password.expose().clone(),
+                "--appendonly".to_string(),
+                "yes".to_string(),
+            ]),
+            _ => None,
+        }
+    }
+
+    /// Whether the env is only used when the data is initialized, like the credentials of SQL databases
+    fn env_initializes_data(&self) -> bool {
+        self.engine != "redis"
+    }
+
+    /// Add "KEY=value" variables to `env`, replacing the ones with the same key,
+    /// and use the credentials that they set
+    fn apply_env(&mut self, extra_env: &[String]) {
+        let env = self.env.get_or_insert_with(Vec::new);
+        for var in extra_env {
+            let (key, value) = var.split_once('=').unwrap_or((var, ""));
+            env.retain(|v| !v.starts_with(&format!("{key}=")));
+            env.push(var.clone());
+
+            if self.username_env.as_deref() == Some(key) {
+                self.username = value.to_string();
+            }
+            if self.password_env.as_deref() == Some(key) {
+                self.password = value.to_string().into();
+            }
+        }
+    }
+
+    /// Command that succeeds once the database accepts connections
+    fn is_ready_cmd(&self) -> Vec<String> {
+        match self.engine.as_str() {
+            "postgres" => vec![
+                "/bin/sh".to_string(),
+                "-c".to_string(),
+                "pg_isready | grep 'accepting connections'".to_string(),
+            ],
+            "redis" => vec![
+                "/bin/sh".to_string(),
+                "-c".to_string(),
+                format!(
+                    "redis-cli -a '{}' --no-auth-warning ping | grep PONG",
+                    self.password.expose()
+                ),
+            ],
+            _ => vec![
+                "mysql".to_string(),
+                format!("-p{}", self.password.expose()),
+                "--silent".to_string(),
+                "-e".to_string(),
+                "show databases;".to_string(),
+            ],
+        }
+    }
+
+    /// Command that writes an SQL dump of the database to stdout
+    fn dump_cmd(&self, database_name: &str) -> Vec<String> {
+        match self.engine.as_str() {
+            "postgres" => vec![
+                "pg_dump".to_string(),
+                "--no-owner".to_string(),
+                "-U".to_string(),
+                self.username.clone(),
+                database_name.to_string(),
+            ],
+            _ => vec![
+                "mysqldump".to_string(),
+                "-u".to_string(),
+                self.username.clone(),
+                format!("-p{}", self.password.expose()),
+                database_name.to_string(),
+            ],
+        }
+    }
+
+    /// Command that runs the SQL it gets on stdin against the database
+    fn restore_cmd(&self, database_name: &str) -> Vec<String> {
+        match self.engine.as_str() {
+            "postgres" => vec![
+                "psql".to_string(),
+                "--quiet".to_string(),
+                "-v".to_string(),
+                "ON_ERROR_STOP=1".to_string(),
+                "-U".to_string(),
+                self.username.clone(),
+                "-d".to_string(),
+                database_name.to_string(),
+            ],
+            _ => vec![
+                "mysql".to_string(),
+                "-u".to_string(),
+                self.username.clone(),
+                format!("-p{}", self.password.expose()),
+                database_name.to_string(),
+            ],
+        }
+    }
+}
+
+/// Whether a container with `current` env was created with another env than `desired`,
+/// leaving out the variables that the image sets itself
+fn env_changed(current: &[String], desired: &[String], image_env: &[String]) -> bool {
+    let own_env = |env: &[String]| -> BTreeSet<String> {
+        env.iter()
+            .filter(|var| !image_env.contains(var))
+            .cloned()
+            .collect()
+    };
+
+    own_env(current) != own_env(desired)
+}
+
+/// Part of a volume name that tells the data of different images apart
+fn image_volume_suffix(image: &str) -> String {
+    image
+        .rsplit('/')
+        .next()
+        .unwrap_or(image)
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+/// Name of the database in a project's local database container.
+/// AWS RDS resources can set their own name, which defaults to the project name like the others.
+fn local_database_name(
+    project_name: &str,
+    db_type: &ResourceType,
+    db_name: Option<String>,
+) -> String {
     match db_type {
+        ResourceType::DatabaseAwsRdsPostgres
+        | ResourceType::DatabaseAwsRdsMySql
+        | ResourceType::DatabaseAwsRdsMariaDB => {
+            db_name.unwrap_or_else(|| project_name.to_string())
+        }
+        // Redis databases are numbered
+        ResourceType::CacheRedis => "0".to_string(),
+        _ => project_name.to_string(),
+    }
+}
+
+/// Names of the database containers a project can have
+fn db_container_names(project_name: &str) -> Vec<String> {
+    [
+        ResourceType::DatabaseSharedPostgres,
+        ResourceType::DatabaseAwsRdsPostgres,
+        ResourceType::DatabaseAwsRdsMariaDB,
+        ResourceType::DatabaseAwsRdsMySql,
+        ResourceType::CacheRedis,
+    ]
+    .into_iter()
+    .filter_map(|db_type| db_type_to_config(db_type, project_name).ok())
+    .map(|config| format!("cyndra_{project_name}_{}", config.r#type))
+    .collect()
+}
+
+fn db_type_to_config(
+    db_type: ResourceType,
+    database_name: &str,
+) -> Result<EngineConfig, ProvisionerError> {
+    let config = match db_type {
         ResourceType::DatabaseSharedPostgres => EngineConfig {
             r#type: "shared_postgres".to_string(),
             image: "docker.io/library/postgres:16".to_string(),
@@ -383,11 +1303,9 @@ fn db_type_to_config(db_type: ResourceType, database_name: &str) -> EngineConfig
                 "POSTGRES_PASSWORD=postgres".to_string(),
                 format!("POSTGRES_DB={database_name}"),
             ]),
-            is_ready_cmd: vec![
-                "/bin/sh".to_string(),
-                "-c".to_string(),
-                "pg_isready | grep 'accepting connections'".to_string(),
-            ],
+            username_env: Some("POSTGRES_USER".to_string()),
+            password_env: Some("POSTGRES_PASSWORD".to_string()),
+            data_dir: "/var/lib/postgresql/data".to_string(),
         },
         ResourceType::DatabaseAwsRdsPostgres => EngineConfig {
             r#type: "aws_rds_postgres".to_string(),
@@ -400,11 +1318,9 @@ fn db_type_to_config(db_type: ResourceType, database_name: &str) -> EngineConfig
                 "POSTGRES_PASSWORD=postgres".to_string(),
                 format!("POSTGRES_DB={database_name}"),
             ]),
-            is_ready_cmd: vec![
-                "/bin/sh".to_string(),
-                "-c".to_string(),
-                "pg_isready | grep 'accepting connections'".to_string(),
-            ],
+            username_env: Some("POSTGRES_USER".to_string()),
+            password_env: Some("POSTGRES_PASSWORD".to_string()),
+            data_dir: "/var/lib/postgresql/data".to_string(),
         },
         ResourceType::DatabaseAwsRdsMariaDB => EngineConfig {
             r#type: "aws_rds_mariadb".to_string(),
@@ -417,13 +1333,10 @@ fn db_type_to_config(db_type: ResourceType, database_name: &str) -> EngineConfig
                 "MARIADB_ROOT_PASSWORD=mariadb".to_string(),
                 format!("MARIADB_DATABASE={database_name}"),
             ]),
-            is_ready_cmd: vec![
-                "mysql".to_string(),
-                "-pmariadb".to_string(),
-                "--silent".to_string(),
-                "-e".to_string(),
-                "show databases;".to_string(),
-            ],
+            // the root user is used, which can not be renamed
+            username_env: None,
+            password_env: Some("MARIADB_ROOT_PASSWORD".to_string()),
+            data_dir: "/var/lib/mysql".to_string(),
         },
         ResourceType::DatabaseAwsRdsMySql => EngineConfig {
             r#type: "aws_rds_mysql".to_string(),
@@ -436,21 +1349,35 @@ fn db_type_to_config(db_type: ResourceType, database_name: &str) -> EngineConfig
                 "MYSQL_ROOT_PASSWORD=mysql".to_string(),
                 format!("MYSQL_DATABASE={database_name}"),
             ]),
-            is_ready_cmd: vec![
-                "mysql".to_string(),
-                "-pmysql".to_string(),
-                "--silent".to_string(),
-                "-e".to_string(),
-                "show databases;".to_string(),
-            ],
+            // the root user is used, which can not be renamed
+            username_env: None,
+            password_env: Some("MYSQL_ROOT_PASSWORD".to_string()),
+            data_dir: "/var/lib/mysql".to_string(),
         },
-        _ => panic!("Non-database resource type provided: {db_type}"),
-    }
+        ResourceType::CacheRedis => EngineConfig {
+            r#type: "redis".to_string(),
+            image: "docker.io/library/redis:7".to_string(),
+            engine: "redis".to_string(),
+            username: "default".to_string(),
+            password: "redis".to_string().into(),
+            port: "6379/tcp".to_string(),
+            // only read by `cmd`, but kept in the env so that a changed password is noticed
+            env: Some(vec!["REDIS_PASSWORD=redis".to_string()]),
+            username_env: None,
+            password_env: Some("REDIS_PASSWORD".to_string()),
+            data_dir: "/data".to_string(),
+        },
+        resource_type => return Err(ProvisionerError::UnsupportedResource { resource_type }),
+    };
+
+    Ok(config)
 }
 
 #[derive(Clone)]
 pub struct ProvApiState {
     pub project_name: String,
+    /// Directory that files in resource configs are relative to
+    pub working_directory: PathBuf,
     pub secrets: HashMap<String, String>,
 }
 
@@ -495,8 +1422,17 @@ pub async fn handler(
             .status(200)
             .body(BoxBody::new(Full::new(Bytes::from(bytes)))),
         Err(e) => {
-            eprintln!("Encountered error when provisioning: {e}");
-            Response::builder().status(500).body(Empty::new().boxed())
+            let status = e.status();
+            // the runtime shows the message of the error, so include its causes
+            let message = format!("{:#}", anyhow::Error::from(e));
+            eprintln!("Encountered error when provisioning: {message}");
+            let error = ApiError::new(message, status);
+            Response::builder()
+                .status(status)
+                .header(CONTENT_TYPE, "application/json")
+                .body(BoxBody::new(Full::new(Bytes::from(
+                    serde_json::to_vec(&error).unwrap(),
+                ))))
         }
     }
 }
@@ -506,7 +1442,7 @@ async fn provision(
     method: Method,
     uri: &str,
     body: Vec<u8>,
-) -> Result<Vec<u8>> {
+) -> Result<Vec<u8>, ProvisionerError> {
     Ok(match (method, uri) {
         (Method::GET, "/projects/proj_LOCAL/resources/secrets") => {
             let response = ResourceResponse {
@@ -520,24 +1456,26 @@ async fn provision(
             serde_json::to_vec(&response).unwrap()
         }
         (Method::POST, "/projects/proj_LOCAL/resources") => {
-            let prov = LocalProvisioner::new().unwrap();
             let cyndra_resource: ProvisionResourceRequest =
-                serde_json::from_slice(&body).context("deserializing resource request")?;
+                serde_json::from_slice(&body).map_err(ProvisionerError::InvalidRequest)?;
+            let prov = LocalProvisioner::new()?;
 
             let response = match cyndra_resource.r#type {
                 ResourceType::DatabaseSharedPostgres
                 | ResourceType::DatabaseAwsRdsMariaDB
                 | ResourceType::DatabaseAwsRdsMySql
-                | ResourceType::DatabaseAwsRdsPostgres => {
+                | ResourceType::DatabaseAwsRdsPostgres
+                | ResourceType::CacheRedis => {
                     let config: DbInput = serde_json::from_value(cyndra_resource.config.clone())
-                        .context("deserializing resource config")?;
-                    let res = prov.get_db_connection_string(
+                        .map_err(ProvisionerError::InvalidRequest)?;
+                    let res = prov
+                        .get_db_connection_string(
                             &state.project_name,
+                            &state.working_directory,
                             cyndra_resource.r#type.clone(),
-                            config.db_name,
+                            config,
                         )
-                        .await
-                        .context("Failed to start database container. Make sure that a Docker engine is running.")?;
+                        .await?;
                     ResourceResponse {
                         r#type: cyndra_resource.r#type,
                         state: resource::ResourceState::Ready,
@@ -547,10 +1485,8 @@ async fn provision(
                 }
                 ResourceType::Container => {
                     let config = serde_json::from_value(cyndra_resource.config.clone())
-                        .context("deserializing resource config")?;
-                    let res = prov.start_container(config)
-                            .await
-                            .context("Failed to start Docker container. Make sure that a Docker engine is running.")?;
+                        .map_err(ProvisionerError::InvalidRequest)?;
+                    let res = prov.start_container(config).await?;
                     ResourceResponse {
                         r#type: cyndra_resource.r#type,
                         state: resource::ResourceState::Ready,
@@ -564,7 +1500,9 @@ async fn provision(
                     config: cyndra_resource.config,
                     output: serde_json::to_value(&state.secrets).unwrap(),
                 },
-                ResourceType::Unknown(s) => bail!("request for unknown resource type {s} recieved"),
+                resource_type @ ResourceType::Unknown(_) => {
+                    return Err(ProvisionerError::UnsupportedResource { resource_type })
+                }
             };
 
             let table = get_resource_tables(&[response.clone()], "local service", false, true);
@@ -572,6 +1510,158 @@ async fn provision(
 
             serde_json::to_vec(&response).unwrap()
         }
-        _ => bail!("Received unsupported resource request"),
+        (method, uri) => {
+            return Err(ProvisionerError::UnsupportedRequest {
+                method,
+                uri: uri.to_string(),
+            })
+        }
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn applies_extra_env() {
+        let mut config = db_type_to_config(ResourceType::DatabaseSharedPostgres, "my-app").unwrap();
+        config.apply_env(&[
+            "POSTGRES_PASSWORD=secret".to_string(),
+            "POSTGRES_USER=app".to_string(),
+            "TZ=UTC".to_string(),
+        ]);
+
+        assert_eq!(
+            config.env.unwrap(),
+            [
+                "POSTGRES_DB=my-app",
+                "POSTGRES_PASSWORD=secret",
+                "POSTGRES_USER=app",
+                "TZ=UTC",
+            ]
+        );
+        assert_eq!(config.username, "app");
+        assert_eq!(config.password.expose(), "secret");
+
+        let mut config = db_type_to_config(ResourceType::CacheRedis, "0").unwrap();
+        config.apply_env(&["REDIS_PASSWORD=secret".to_string()]);
+
+        assert_eq!(config.env.as_deref().unwrap(), ["REDIS_PASSWORD=secret"]);
+        assert_eq!(config.password.expose(), "secret");
+        assert!(config.cmd().unwrap().contains(&"secret".to_string()));
+        assert!(config.is_ready_cmd()[2].contains("-a 'secret'"));
+    }
+
+    #[test]
+    fn detects_env_changes() {
+        let image_env = ["PATH=/usr/bin".to_string(), "PG_MAJOR=16".to_string()];
+        let current = [
+            "POSTGRES_PASSWORD=postgres".to_string(),
+            "POSTGRES_DB=my-app".to_string(),
+            "PATH=/usr/bin".to_string(),
+            "PG_MAJOR=16".to_string(),
+        ];
+
+        let mut desired = vec![
+            "POSTGRES_DB=my-app".to_string(),
+            "POSTGRES_PASSWORD=postgres".to_string(),
+        ];
+        assert!(!env_changed(&current, &desired, &image_env));
+
+        desired[1] = "POSTGRES_PASSWORD=secret".to_string();
+        assert!(env_changed(&current, &desired, &image_env));
+
+        desired.truncate(1);
+        assert!(env_changed(&current, &desired, &image_env));
+    }
+
+    #[test]
+    fn names_image_volumes() {
+        assert_eq!(
+            image_volume_suffix("docker.io/library/postgres:15"),
+            "postgres_15"
+        );
+        assert_eq!(
+            image_volume_suffix("pgvector/pgvector:pg15"),
+            "pgvector_pg15"
+        );
+        assert_eq!(image_volume_suffix("postgis/postgis"), "postgis");
+    }
+
+    #[test]
+    fn names_db_containers() {
+        assert_eq!(
+            db_container_names("my-app"),
+            [
+                "cyndra_my-app_shared_postgres",
+                "cyndra_my-app_aws_rds_postgres",
+                "cyndra_my-app_aws_rds_mariadb",
+                "cyndra_my-app_aws_rds_mysql",
+                "cyndra_my-app_redis",
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_docker_errors() {
+        let error = ProvisionerError::from_docker(
+            bollard::errors::Error::SocketNotFoundError("/run/user/1000/docker.sock".to_string()),
+            |source| ProvisionerError::InspectContainer {
+                container: "cyndra_my-app_shared_postgres".to_string(),
+                source,
+            },
+        );
+        assert!(matches!(error, ProvisionerError::Connect(_)));
+
+        let error = ProvisionerError::from_docker(
+            bollard::errors::Error::DockerResponseServerError {
+                status_code: 500,
+                message: "port is already allocated".to_string(),
+            },
+            |source| ProvisionerError::StartContainer {
+                container: "cyndra_my-app_shared_postgres".to_string(),
+                host_port: "5432".to_string(),
+                source,
+            },
+        );
+        let message = format!("{:#}", anyhow::Error::from(error));
+        assert!(message.starts_with("failed to start container 'cyndra_my-app_shared_postgres'"));
+        assert!(message.contains("host port 5432"));
+        assert!(message.ends_with("port is already allocated"));
+
+        assert!(matches!(
+            db_type_to_config(ResourceType::Secrets, "my-app"),
+            Err(ProvisionerError::UnsupportedResource { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn responds_with_error_status() {
+        let state = Arc::new(ProvApiState {
+            project_name: "my-app".to_string(),
+            working_directory: PathBuf::new(),
+            secrets: HashMap::new(),
+        });
+
+        let error = provision(state.clone(), Method::GET, "/projects", Vec::new())
+            .await
+            .unwrap_err();
+        assert_eq!(error.status(), StatusCode::NOT_FOUND);
+
+        let error = provision(
+            state,
+            Method::POST,
+            "/projects/proj_LOCAL/resources",
+            b"{".to_vec(),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(error.status(), StatusCode::BAD_REQUEST);
+
+        let error = ProvisionerError::EnvChanged {
+            container: "cyndra_my-app_shared_postgres".to_string(),
+        };
+        assert_eq!(error.status(), StatusCode::CONFLICT);
+    }
+}
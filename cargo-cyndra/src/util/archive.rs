@@ -0,0 +1,418 @@
+use std::{
+    collections::HashSet,
+    fmt::Write as _,
+    fs::File,
+    io::{self, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    sync::LazyLock,
+};
+
+use anyhow::{Context, Result};
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+/// Files larger than this are pointed out in a dry run
+pub const LARGE_FILE_WARNING_BYTES: u64 = 10 * 1024 * 1024;
+
+/// Why a file is or is not in the deployment archive
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "reason", content = "pattern", rename_all = "snake_case")]
+pub enum ArchiveReason {
+    /// Not matched by any ignore rule
+    NotIgnored,
+    /// Matched by an `include` glob in Cyndra.toml
+    IncludeGlob(String),
+    /// A Secrets.toml file, or the secrets file given with `--secrets`
+    SecretsFile,
+    /// Matched by one of the default excludes, like `target/`
+    DefaultExclude(String),
+    /// Matched by a rule in .gitignore, .ignore or a global git ignore file
+    Ignored,
+    Symlink,
+}
+
+impl ArchiveReason {
+    pub fn is_included(&self) -> bool {
+        matches!(
+            self,
+            Self::NotIgnored | Self::IncludeGlob(_) | Self::SecretsFile
+        )
+    }
+}
+
+impl std::fmt::Display for ArchiveReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotIgnored => write!(f, "not ignored"),
+            Self::IncludeGlob(pattern) => write!(f, "include glob '{pattern}'"),
+            Self::SecretsFile => write!(f, "secrets file"),
+            Self::DefaultExclude(pattern) => write!(f, "excluded by default ({pattern})"),
+            Self::Ignored => write!(f, "ignore rules"),
+            Self::Symlink => write!(f, "symlink"),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ArchiveEntry {
+    /// Path relative to the project, with `/` separators
+    pub name: String,
+    pub is_dir: bool,
+    /// Size of files, not known for excluded directories
+    pub size: Option<u64>,
+    #[serde(flatten)]
+    pub reason: ArchiveReason,
+}
+
+/// Files and directories in the project that are not in the archive.
+/// Excluded directories are listed once instead of listing everything in them,
+/// unless some of their files are included.
+pub fn excluded_entries(
+    working_directory: &Path,
+    included: &HashSet<PathBuf>,
+    default_excludes: &[String],
+) -> Result<Vec<ArchiveEntry>> {
+    let mut entries = Vec::new();
+    let mut walk = walkdir::WalkDir::new(working_directory)
+        .min_depth(1)
+        .into_iter();
+    while let Some(entry) = walk.next() {
+        let entry = entry.context("list dir")?;
+        let path = entry.path();
+        if included.contains(path) {
+            continue;
+        }
+        let is_dir = entry.file_type().is_dir();
+        if is_dir && included.iter().any(|p| p.starts_with(path)) {
+            // show the excluded files in it next to the included ones
+            continue;
+        }
+
+        let name = archive_name(working_directory, path)?;
+        // the directories that this entry is in, or is
+        let dirs = match name.rsplit_once('/') {
+            _ if is_dir => name.as_str(),
+            Some((dirs, _)) => dirs,
+            None => "",
+        };
+        let reason = if entry.path_is_symlink() {
+            ArchiveReason::Symlink
+        } else if let Some(pattern) = default_excludes
+            .iter()
+            .find(|p| dirs.split('/').any(|d| d == p.trim_end_matches('/')))
+        {
+            ArchiveReason::DefaultExclude(pattern.to_string())
+        } else {
+            ArchiveReason::Ignored
+        };
+        if is_dir {
+            walk.skip_current_dir();
+        }
+
+        entries.push(ArchiveEntry {
+            name,
+            is_dir,
+            size: (!is_dir)
+                .then(|| entry.metadata().map(|m| m.len()))
+                .transpose()?,
+            reason,
+        });
+    }
+
+    Ok(entries)
+}
+
+/// Name of a file in the archive, which is its path in the project with `/` separators
+pub fn archive_name(working_directory: &Path, path: &Path) -> Result<String> {
+    Ok(path
+        .strip_prefix(working_directory)
+        .context("strip prefix of path")?
+        .to_str()
+        .context("valid filename")?
+        // windows things
+        .replace('\\', "/"))
+}
+
+/// Files that are often secrets, and should not be deployed by accident
+static SECRET_FILE_GLOBS: LazyLock<GlobSet> = LazyLock::new(|| {
+    let mut globs = GlobSetBuilder::new();
+    for pattern in [
+        "**/.env",
+        "**/.env.*",
+        "**/*.pem",
+        "**/*.key",
+        "**/*.p12",
+        "**/*.pfx",
+        "**/id_rsa*",
+        "**/id_ed25519*",
+        "**/.npmrc",
+        "**/.netrc",
+        "**/credentials*.json",
+    ] {
+        globs.add(Glob::new(pattern).expect("valid glob"));
+    }
+
+    globs.build().expect("valid glob set")
+});
+
+/// Whether an archive entry looks like it holds secrets that are not meant to be deployed
+pub fn looks_like_secret(name: &str) -> bool {
+    SECRET_FILE_GLOBS.is_match(name)
+}
+
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{bytes} B")
+    } else {
+        format!("{size:.1} {}", UNITS[unit])
+    }
+}
+
+/// Render entries sorted by name as an indented tree, marking included entries with `+`
+pub fn format_tree(entries: &[ArchiveEntry]) -> String {
+    let mut out = String::new();
+    let mut open_dirs: Vec<&str> = Vec::new();
+    for entry in entries {
+        let parts = entry.name.split('/').collect::<Vec<_>>();
+        let (file, dirs) = parts.split_last().expect("split to yield one part");
+        let common = open_dirs
+            .iter()
+            .zip(dirs)
+            .take_while(|(a, b)| a == b)
+            .count();
+        for (depth, dir) in dirs.iter().enumerate().skip(common) {
+            writeln!(out, "  {}{dir}/", "  ".repeat(depth)).unwrap();
+        }
+        open_dirs = dirs.to_vec();
+
+        let marker = if entry.reason.is_included() { '+' } else { '-' };
+        let label = format!(
+            "{}{file}{}",
+            "  ".repeat(dirs.len()),
+            if entry.is_dir { "/" } else { "" }
+        );
+        let size = entry.size.map(format_size).unwrap_or_default();
+        writeln!(out, "{marker} {label:<40} {size:>10}  {}", entry.reason).unwrap();
+    }
+
+    out
+}
+
+/// Writer for the archive that fails writes once it grows past a size limit,
+/// so that packing stops early instead of building a huge archive
+pub struct LimitedWriter<W> {
+    inner: W,
+    limit: u64,
+    /// Position in `inner`, which the zip writer seeks back in to fill in headers
+    pos: u64,
+    exceeded: bool,
+}
+
+impl<W: Write + Seek> LimitedWriter<W> {
+    pub fn new(inner: W, limit: u64) -> Self {
+        Self {
+            inner,
+            limit,
+            pos: 0,
+            exceeded: false,
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: Write + Seek> Write for LimitedWriter<W> {
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        // the archive is discarded after the error, but the zip writer still finishes it when dropped
+        if self.exceeded {
+            return Ok(data.len());
+        }
+        let written = self.inner.write(data)?;
+        self.pos += written as u64;
+        if self.pos > self.limit {
+            self.exceeded = true;
+            return Err(io::Error::other(format!(
+                "the archive is larger than the maximum of {}. \
+                Exclude large files, or raise `max_archive_size_mib` in the deploy section of Cyndra.toml",
+                format_size(self.limit)
+            )));
+        }
+
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<W: Write + Seek> Seek for LimitedWriter<W> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.pos = self.inner.seek(pos)?;
+
+        Ok(self.pos)
+    }
+}
+
+/// A packed deployment archive, kept in a temporary file so that large archives are not held in memory
+#[derive(Debug)]
+pub struct Archive {
+    /// Positioned at the start of the archive
+    pub file: File,
+    pub size: u64,
+    pub sha256: String,
+}
+
+impl Archive {
+    /// Take a finished archive file, and hash it for comparing it with uploaded archives
+    pub fn new(mut file: File) -> Result<Self> {
+        file.rewind().context("rewind archive")?;
+        let mut hasher = Sha256::new();
+        let size = io::copy(&mut file, &mut hasher).context("hash archive")?;
+        file.rewind().context("rewind archive")?;
+
+        Ok(Self {
+            file,
+            size,
+            sha256: format!("{:x}", hasher.finalize()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(name: &str, size: Option<u64>, reason: ArchiveReason) -> ArchiveEntry {
+        ArchiveEntry {
+            name: name.to_owned(),
+            is_dir: size.is_none(),
+            size,
+            reason,
+        }
+    }
+
+    #[test]
+    fn finds_excluded_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        for file in [
+            ".git/HEAD",
+            "notes.txt",
+            "src/main.rs",
+            "target/debug/app",
+            "target/x",
+        ] {
+            std::fs::create_dir_all(root.join(file).parent().unwrap()).unwrap();
+            std::fs::write(root.join(file), "").unwrap();
+        }
+        let included = HashSet::from([root.join("src/main.rs"), root.join("target/debug/app")]);
+
+        let mut excluded =
+            excluded_entries(root, &included, &[".git/".to_owned(), "target/".to_owned()])
+                .unwrap()
+                .into_iter()
+                .map(|e| (e.name, e.reason))
+                .collect::<Vec<_>>();
+        excluded.sort_by(|a, b| a.0.cmp(&b.0));
+
+        assert_eq!(
+            excluded,
+            [
+                (
+                    ".git".to_owned(),
+                    ArchiveReason::DefaultExclude(".git/".to_owned())
+                ),
+                ("notes.txt".to_owned(), ArchiveReason::Ignored),
+                (
+                    "target/x".to_owned(),
+                    ArchiveReason::DefaultExclude("target/".to_owned())
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn limits_writer_size() {
+        let mut writer = LimitedWriter::new(io::Cursor::new(Vec::new()), 4);
+        writer.write_all(b"1234").unwrap();
+        // rewriting what is already there does not grow it
+        writer.seek(SeekFrom::Start(0)).unwrap();
+        writer.write_all(b"12").unwrap();
+        writer.seek(SeekFrom::End(0)).unwrap();
+        assert!(writer.write_all(b"5").is_err());
+    }
+
+    #[test]
+    fn hashes_archive_file() {
+        let mut file = tempfile::tempfile().unwrap();
+        file.write_all(b"abc").unwrap();
+        let mut archive = Archive::new(file).unwrap();
+
+        assert_eq!(archive.size, 3);
+        assert_eq!(
+            archive.sha256,
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        let mut content = String::new();
+        io::Read::read_to_string(&mut archive.file, &mut content).unwrap();
+        assert_eq!(content, "abc");
+    }
+
+    #[test]
+    fn formats_sizes() {
+        assert_eq!(format_size(0), "0 B");
+        assert_eq!(format_size(1023), "1023 B");
+        assert_eq!(format_size(1536), "1.5 KiB");
+        assert_eq!(format_size(LARGE_FILE_WARNING_BYTES), "10.0 MiB");
+    }
+
+    #[test]
+    fn finds_secret_looking_files() {
+        assert!(looks_like_secret(".env"));
+        assert!(looks_like_secret("api/.env.production"));
+        assert!(looks_like_secret("certs/server.key"));
+        assert!(looks_like_secret("id_rsa.pub"));
+        assert!(!looks_like_secret("Secrets.toml"));
+        assert!(!looks_like_secret("src/env.rs"));
+    }
+
+    #[test]
+    fn formats_tree() {
+        let entries = [
+            entry("Cargo.toml", Some(120), ArchiveReason::NotIgnored),
+            entry("src/bin/app.rs", Some(2048), ArchiveReason::NotIgnored),
+            entry("src/main.rs", Some(10), ArchiveReason::NotIgnored),
+            entry(
+                "target",
+                None,
+                ArchiveReason::DefaultExclude("target/".to_owned()),
+            ),
+        ];
+
+        assert_eq!(
+            format_tree(&entries),
+            [
+                "+ Cargo.toml                                    120 B  not ignored",
+                "  src/",
+                "    bin/",
+                "+     app.rs                                  2.0 KiB  not ignored",
+                "+   main.rs                                      10 B  not ignored",
+                "- target/                                              excluded by default (target/)",
+                "",
+            ]
+            .join("\n")
+        );
+    }
+}
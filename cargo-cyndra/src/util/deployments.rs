@@ -0,0 +1,382 @@
+use std::{collections::BTreeSet, path::Path};
+
+use anyhow::{bail, Context, Result};
+use git2::{Commit, ObjectType, Repository, Sort};
+use serde::Serialize;
+use serde_json::Value;
+use cyndra_common::models::deployment::{DeploymentResponse, DeploymentState};
+
+/// Whether a deployment was built and started, so that it can be redeployed
+pub fn is_successful(deployment: &DeploymentResponse) -> bool {
+    matches!(
+        deployment.state,
+        DeploymentState::Running | DeploymentState::Stopped
+    ) && deployment.build_id.is_some()
+}
+
+/// Find the successful deployment `steps` successful deployments before `current`.
+/// `deployments` should be ordered newest first, like the API returns them.
+pub fn find_previous_successful<'a>(
+    deployments: &'a [DeploymentResponse],
+    current: Option<&DeploymentResponse>,
+    steps: usize,
+) -> Option<&'a DeploymentResponse> {
+    deployments
+        .iter()
+        .filter(|d| current.is_none_or(|c| d.id != c.id && d.created_at < c.created_at))
+        .filter(|d| is_successful(d))
+        .nth(steps.checked_sub(1)?)
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct CommitSummary {
+    pub id: String,
+    pub summary: String,
+}
+
+/// Commits that are reachable from `to` but not from `from`, newest first
+pub fn commits_between(repo: &Repository, from: &str, to: &str) -> Result<Vec<CommitSummary>> {
+    let find = |rev: &str| {
+        repo.revparse_single(rev)
+            .and_then(|o| o.peel_to_commit())
+            .map(|c| c.id())
+            .with_context(|| {
+                format!("commit {rev} was not found in the local repository, try `git fetch`")
+            })
+    };
+
+    let mut walk = repo.revwalk()?;
+    walk.set_sorting(Sort::TOPOLOGICAL | Sort::TIME)?;
+    walk.push(find(to)?)?;
+    walk.hide(find(from)?)?;
+
+    walk.map(|id| {
+        let commit = repo.find_commit(id?)?;
+        Ok(CommitSummary {
+            id: commit.id().to_string(),
+            summary: commit.summary().unwrap_or_default().to_owned(),
+        })
+    })
+    .collect()
+}
+
+/// What a git ref that is deployed from names
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum GitRefKind {
+    Branch,
+    Tag,
+    /// A commit id, or another revision like `HEAD~1`
+    Commit,
+}
+
+/// Resolve a branch, tag or commit to deploy from, and check that `subdirectory` exists in it.
+///
+/// Branches are resolved at the remote-tracking branch of `repo_url`, since that is what gets cloned.
+/// Fails if the local branch differs from it, as it is then unclear what should be deployed.
+pub fn resolve_git_ref<'r>(
+    repo: &'r Repository,
+    repo_url: &str,
+    git_ref: &str,
+    subdirectory: Option<&str>,
+) -> Result<(Commit<'r>, GitRefKind)> {
+    let find = |rev: &str| repo.revparse_single(rev).and_then(|o| o.peel_to_commit());
+    let remote_ref = find_remote(repo, repo_url).map(|remote| format!("{remote}/{git_ref}"));
+    let remote_commit = remote_ref.as_deref().map(find);
+    // symbolic refs like HEAD resolve to the branch they point at, which was not what was given
+    let kind = match repo
+        .resolve_reference_from_short_name(git_ref)
+        .ok()
+        .filter(|r| r.shorthand() == Some(git_ref))
+    {
+        _ if matches!(remote_commit, Some(Ok(_))) => GitRefKind::Branch,
+        Some(reference) if reference.is_branch() => GitRefKind::Branch,
+        Some(reference) if reference.is_tag() => GitRefKind::Tag,
+        _ => GitRefKind::Commit,
+    };
+    let commit = match (find(git_ref), remote_commit) {
+        (Ok(local), Some(Ok(remote))) if local.id() != remote.id() => bail!(
+            "ref '{git_ref}' is at {} locally but at {} on the remote. \
+            Push it, or run `git fetch` if the remote is ahead.",
+            local.id(),
+            remote.id()
+        ),
+        (_, Some(Ok(remote))) => remote,
+        (Ok(local), _) => local,
+        (Err(_), _) => {
+            bail!("ref '{git_ref}' was not found in the local repository, try `git fetch`")
+        }
+    };
+
+    if let Some(subdirectory) = subdirectory {
+        let entry = commit
+            .tree()?
+            .get_path(Path::new(subdirectory.trim_matches('/')))
+            .with_context(|| format!("'{subdirectory}' does not exist at ref '{git_ref}'"))?;
+        if entry.kind() != Some(ObjectType::Tree) {
+            bail!("'{subdirectory}' is not a directory at ref '{git_ref}'");
+        }
+    }
+
+    Ok((commit, kind))
+}
+
+/// Name of the remote that fetches from `url`, ignoring a trailing `.git` or `/`
+fn find_remote(repo: &Repository, url: &str) -> Option<String> {
+    let normalize = |url: &str| {
+        let url = url.trim_end_matches('/');
+        url.strip_suffix(".git").unwrap_or(url).to_owned()
+    };
+    let url = normalize(url);
+    let remotes = repo.remotes().ok()?;
+
+    remotes
+        .iter()
+        .flatten()
+        .find(|name| {
+            repo.find_remote(name)
+                .is_ok_and(|r| r.url().map(normalize) == Some(url.clone()))
+        })
+        .map(ToOwned::to_owned)
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+pub struct BuildArgChange {
+    pub field: String,
+    pub from: Value,
+    pub to: Value,
+}
+
+/// Fields that have different values in the two sets of build args.
+/// The args are compared as JSON, so that build args of any type and version can be diffed.
+pub fn build_args_changes(from: &Value, to: &Value) -> Vec<BuildArgChange> {
+    let fields = |args: &Value| {
+        let mut fields = match args.get("content") {
+            Some(Value::Object(content)) => content.clone(),
+            _ => Default::default(),
+        };
+        if let Some(r#type) = args.get("type") {
+            fields.insert("type".to_owned(), r#type.clone());
+        }
+        fields
+    };
+    let from = fields(from);
+    let to = fields(to);
+
+    from.keys()
+        .chain(to.keys())
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .filter_map(|field| {
+            let before = from.get(field).cloned().unwrap_or_default();
+            let after = to.get(field).cloned().unwrap_or_default();
+            (before != after).then(|| BuildArgChange {
+                field: field.clone(),
+                from: before,
+                to: after,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::DateTime;
+    use git2::{Oid, Signature};
+    use cyndra_common::models::deployment::{BuildArgs, BuildArgsRust};
+
+    use super::*;
+
+    fn deployment(id: u8, state: DeploymentState, built: bool) -> DeploymentResponse {
+        let time = DateTime::from_timestamp(id as i64, 0).unwrap();
+        DeploymentResponse {
+            id: id.to_string(),
+            state,
+            created_at: time,
+            updated_at: time,
+            uris: Vec::new(),
+            build_id: built.then(|| format!("build-{id}")),
+            build_meta: None,
+            build_args: None,
+        }
+    }
+
+    #[test]
+    fn finds_previous_successful() {
+        // newest first
+        let deployments = vec![
+            deployment(6, DeploymentState::Failed, true),
+            deployment(5, DeploymentState::Running, true),
+            deployment(4, DeploymentState::Failed, false),
+            deployment(3, DeploymentState::Stopped, true),
+            deployment(2, DeploymentState::Stopped, false),
+            deployment(1, DeploymentState::Stopped, true),
+        ];
+        let current = &deployments[1];
+        let find = |steps| {
+            find_previous_successful(&deployments, Some(current), steps).map(|d| d.id.as_str())
+        };
+
+        assert_eq!(find(1), Some("3"));
+        assert_eq!(find(2), Some("1"));
+        assert_eq!(find(3), None);
+        assert_eq!(find(0), None);
+
+        assert_eq!(
+            find_previous_successful(&deployments, None, 1).map(|d| d.id.as_str()),
+            Some("5")
+        );
+    }
+
+    #[test]
+    fn lists_commits_between() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let sig = Signature::now("test", "test@example.com").unwrap();
+        let tree = repo
+            .find_tree(repo.index().unwrap().write_tree().unwrap())
+            .unwrap();
+        let mut commits: Vec<Oid> = Vec::new();
+        for msg in ["first", "second", "third"] {
+            let parents = commits
+                .last()
+                .map(|id| repo.find_commit(*id).unwrap())
+                .into_iter()
+                .collect::<Vec<_>>();
+            let parents = parents.iter().collect::<Vec<_>>();
+            commits.push(
+                repo.commit(Some("HEAD"), &sig, &sig, msg, &tree, &parents)
+                    .unwrap(),
+            );
+        }
+        let first = commits[0].to_string();
+        let third = commits[2].to_string();
+
+        let summaries = commits_between(&repo, &first, &third)
+            .unwrap()
+            .into_iter()
+            .map(|c| c.summary)
+            .collect::<Vec<_>>();
+        assert_eq!(summaries, ["third", "second"]);
+
+        // short ids work too
+        assert!(commits_between(&repo, &third, &first[..7])
+            .unwrap()
+            .is_empty());
+        assert!(commits_between(&repo, &first, "0000000").is_err());
+    }
+
+    #[test]
+    fn resolves_git_refs() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let sig = Signature::now("test", "test@example.com").unwrap();
+        std::fs::create_dir(dir.path().join("app")).unwrap();
+        std::fs::write(dir.path().join("app/Cargo.toml"), "").unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("app/Cargo.toml")).unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let id = repo
+            .commit(Some("HEAD"), &sig, &sig, "first", &tree, &[])
+            .unwrap();
+        repo.tag_lightweight("v1", &repo.find_object(id, None).unwrap(), false)
+            .unwrap();
+
+        let short = id.to_string()[..7].to_owned();
+        let url = "https://example.com/app.git";
+        for (git_ref, kind) in [
+            ("HEAD", GitRefKind::Commit),
+            ("v1", GitRefKind::Tag),
+            (short.as_str(), GitRefKind::Commit),
+        ] {
+            let (commit, resolved_kind) = resolve_git_ref(&repo, url, git_ref, None).unwrap();
+            assert_eq!((commit.id(), resolved_kind), (id, kind));
+        }
+        assert!(resolve_git_ref(&repo, url, "v2", None).is_err());
+
+        assert!(resolve_git_ref(&repo, url, "v1", Some("app")).is_ok());
+        assert!(resolve_git_ref(&repo, url, "v1", Some("app/")).is_ok());
+        assert!(resolve_git_ref(&repo, url, "v1", Some("api")).is_err());
+        assert!(resolve_git_ref(&repo, url, "v1", Some("app/Cargo.toml")).is_err());
+
+        // branches are resolved on the remote of the url
+        let second = repo
+            .commit(
+                Some("refs/heads/feature"),
+                &sig,
+                &sig,
+                "second",
+                &tree,
+                &[&repo.find_commit(id).unwrap()],
+            )
+            .unwrap();
+        repo.remote("origin", "https://example.com/app").unwrap();
+        repo.reference("refs/remotes/origin/feature", id, false, "")
+            .unwrap();
+        repo.reference("refs/remotes/origin/remote-only", id, false, "")
+            .unwrap();
+        assert!(resolve_git_ref(&repo, url, "feature", None).is_err());
+        let (commit, kind) = resolve_git_ref(&repo, url, "remote-only", None).unwrap();
+        assert_eq!((commit.id(), kind), (id, GitRefKind::Branch));
+        repo.reference("refs/remotes/origin/feature", second, true, "")
+            .unwrap();
+        let (commit, kind) = resolve_git_ref(&repo, url, "feature", None).unwrap();
+        assert_eq!((commit.id(), kind), (second, GitRefKind::Branch));
+
+        // branch names that look like commit ids are still branches
+        let hex_branch = &id.to_string()[..6];
+        repo.reference(&format!("refs/heads/{hex_branch}"), second, false, "")
+            .unwrap();
+        let (commit, kind) = resolve_git_ref(&repo, url, hex_branch, None).unwrap();
+        assert_eq!((commit.id(), kind), (second, GitRefKind::Branch));
+    }
+
+    #[test]
+    fn finds_build_args_changes() {
+        let from = serde_json::to_value(BuildArgs::Rust(BuildArgsRust::default())).unwrap();
+        let to = serde_json::to_value(BuildArgs::Rust(BuildArgsRust {
+            features: Some("a,b".to_owned()),
+            mold: true,
+            ..Default::default()
+        }))
+        .unwrap();
+
+        assert!(build_args_changes(&from, &from).is_empty());
+        assert_eq!(
+            build_args_changes(&from, &to),
+            [
+                BuildArgChange {
+                    field: "features".to_owned(),
+                    from: Value::Null,
+                    to: "a,b".into(),
+                },
+                BuildArgChange {
+                    field: "mold".to_owned(),
+                    from: false.into(),
+                    to: true.into(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn finds_changes_in_unknown_build_args() {
+        let deployment: DeploymentResponse = serde_json::from_value(serde_json::json!({
+            "id": "depl_1",
+            "state": "running",
+            "created_at": "2024-01-01T00:00:00Z",
+            "updated_at": "2024-01-01T00:00:00Z",
+            "uris": [],
+            "build_args": {"type": "Python", "content": {"python_version": "3.12"}},
+        }))
+        .unwrap();
+        let from = serde_json::to_value(BuildArgs::Rust(BuildArgsRust::default())).unwrap();
+
+        let changes = build_args_changes(&from, deployment.build_args.as_ref().unwrap());
+        assert!(changes.iter().any(|c| c.field == "python_version"));
+        assert!(changes.contains(&BuildArgChange {
+            field: "type".to_owned(),
+            from: "Rust".into(),
+            to: "Python".into(),
+        }));
+    }
+}
@@ -0,0 +1,136 @@
+use std::{path::Path, process::Stdio};
+
+use anyhow::{bail, Context, Result};
+use indoc::formatdoc;
+
+/// Base image for locally built images, which only need to run the compiled binary
+const RUNTIME_BASE_IMAGE: &str = "debian:bookworm-slim";
+
+/// Dockerfile that copies an already built binary into a slim image
+pub fn dockerfile(binary_name: &str) -> String {
+    formatdoc! {r#"
+        FROM {RUNTIME_BASE_IMAGE}
+        RUN apt-get update \
+            && apt-get install -y --no-install-recommends ca-certificates \
+            && rm -rf /var/lib/apt/lists/*
+        COPY {binary_name} /usr/local/bin/{binary_name}
+        ENTRYPOINT ["/usr/local/bin/{binary_name}"]
+    "#}
+}
+
+/// Name of the repository in `registry` that images of a project are pushed to
+pub fn image_repository(registry: &str, project_name: &str) -> String {
+    format!("{}/{project_name}", registry.trim_end_matches('/'))
+}
+
+/// Find the digest reference of a pushed image in the output of
+/// `docker image inspect --format '{{json .RepoDigests}}'`
+pub fn find_repo_digest(repo_digests: &str, repository: &str) -> Result<Option<String>> {
+    let digests: Vec<String> =
+        serde_json::from_str(repo_digests.trim()).context("parsing image repo digests")?;
+
+    let repository = normalize_repository(repository);
+
+    Ok(digests.into_iter().find(|d| {
+        d.split_once('@')
+            .is_some_and(|(repo, _)| normalize_repository(repo) == repository)
+    }))
+}
+
+/// Docker's short form of a repository name, which it uses for Docker Hub repositories,
+/// like `user/app` for `docker.io/user/app` and `postgres` for `docker.io/library/postgres`
+fn normalize_repository(repository: &str) -> &str {
+    let repository = ["docker.io/", "index.docker.io/", "registry-1.docker.io/"]
+        .iter()
+        .find_map(|host| repository.strip_prefix(host))
+        .unwrap_or(repository);
+
+    repository.strip_prefix("library/").unwrap_or(repository)
+}
+
+/// Run a docker command, sending its output to stderr
+pub async fn run(args: &[&str], dir: &Path) -> Result<()> {
+    let status = tokio::process::Command::new("docker")
+        .args(args)
+        .current_dir(dir)
+        .stdout(std::io::stderr())
+        .kill_on_drop(true)
+        .status()
+        .await
+        .context("failed to run docker, is it installed?")?;
+    if !status.success() {
+        bail!("`docker {}` failed with {status}", args.join(" "));
+    }
+
+    Ok(())
+}
+
+/// Run a docker command and return its output
+pub async fn output(args: &[&str]) -> Result<String> {
+    let output = tokio::process::Command::new("docker")
+        .args(args)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .output()
+        .await
+        .context("failed to run docker, is it installed?")?;
+    if !output.status.success() {
+        bail!(
+            "`docker {}` failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    String::from_utf8(output.stdout).context("docker output should be valid utf8")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_repo_digest() {
+        let repository = image_repository("ghcr.io/my-org/", "my-app");
+        assert_eq!(repository, "ghcr.io/my-org/my-app");
+
+        let output = r#"["docker.io/other/my-app@sha256:aaa","ghcr.io/my-org/my-app@sha256:bbb"]"#;
+        assert_eq!(
+            find_repo_digest(output, &repository).unwrap().as_deref(),
+            Some("ghcr.io/my-org/my-app@sha256:bbb")
+        );
+        assert_eq!(find_repo_digest("[]\n", &repository).unwrap(), None);
+        assert!(find_repo_digest("null", &repository).is_err());
+    }
+
+    #[test]
+    fn finds_docker_hub_repo_digest() {
+        let output = r#"["my-user/my-app@sha256:aaa"]"#;
+        for registry in ["docker.io/my-user", "index.docker.io/my-user/", "my-user"] {
+            let repository = image_repository(registry, "my-app");
+            assert_eq!(
+                find_repo_digest(output, &repository).unwrap().as_deref(),
+                Some("my-user/my-app@sha256:aaa"),
+                "registry {registry}"
+            );
+        }
+
+        let output = r#"["my-app@sha256:bbb"]"#;
+        assert_eq!(
+            find_repo_digest(output, "docker.io/library/my-app")
+                .unwrap()
+                .as_deref(),
+            Some("my-app@sha256:bbb")
+        );
+        assert_eq!(find_repo_digest(output, "ghcr.io/my-app").unwrap(), None);
+    }
+
+    #[test]
+    fn generates_dockerfile() {
+        let dockerfile = dockerfile("my-app");
+        assert!(dockerfile.starts_with("FROM debian:bookworm-slim\n"));
+        assert!(dockerfile.contains("COPY my-app /usr/local/bin/my-app\n"));
+        assert!(dockerfile.ends_with("ENTRYPOINT [\"/usr/local/bin/my-app\"]\n"));
+    }
+}
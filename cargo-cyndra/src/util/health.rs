@@ -0,0 +1,102 @@
+use std::{net::SocketAddr, time::Duration};
+
+use reqwest::{Client, StatusCode};
+use tokio::{io::AsyncReadExt, net::TcpStream};
+
+/// How long a connection has to stay open for the TCP probe to pass
+const TCP_PROBE_SETTLE_TIME: Duration = Duration::from_millis(200);
+
+/// URL to probe for one of the URIs of a deployment
+pub fn probe_url(uri: &str, path: &str) -> String {
+    format!(
+        "{}/{}",
+        uri.trim_end_matches('/'),
+        path.trim_start_matches('/')
+    )
+}
+
+/// Send one request to `url`, returning why the probe failed if it did
+pub async fn probe(
+    client: &Client,
+    url: &str,
+    expected: StatusCode,
+    timeout: Duration,
+) -> Result<(), String> {
+    match client.get(url).timeout(timeout).send().await {
+        Ok(res) if res.status() == expected => Ok(()),
+        Ok(res) => Err(format!(
+            "{url} responded with {}, expected {expected}",
+            res.status()
+        )),
+        Err(e) if e.is_timeout() => Err(format!("{url} did not respond within {timeout:?}")),
+        Err(e) => Err(format!("{url} could not be reached: {e}")),
+    }
+}
+
+/// Connect to `addr`, returning why the probe failed if it did.
+///
+/// Docker accepts connections on published ports even before anything listens in the container,
+/// and then closes them, so a connection only counts if it is not closed right away.
+pub async fn probe_tcp(addr: SocketAddr, timeout: Duration) -> Result<(), String> {
+    let mut stream = match tokio::time::timeout(timeout, TcpStream::connect(addr)).await {
+        Ok(Ok(stream)) => stream,
+        Ok(Err(e)) => return Err(format!("{addr} could not be reached: {e}")),
+        Err(_) => {
+            return Err(format!(
+                "{addr} did not accept a connection within {timeout:?}"
+            ))
+        }
+    };
+
+    match tokio::time::timeout(TCP_PROBE_SETTLE_TIME, stream.read(&mut [0; 1])).await {
+        Ok(Ok(0)) => Err(format!("{addr} closed the connection")),
+        Ok(Err(e)) => Err(format!("{addr} could not be read from: {e}")),
+        // either the server sent something or it is waiting for the client
+        Ok(Ok(_)) | Err(_) => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::net::TcpListener;
+
+    use super::*;
+
+    #[test]
+    fn builds_probe_urls() {
+        assert_eq!(
+            probe_url("https://my-app.cyndra.app/", "/healthz"),
+            "https://my-app.cyndra.app/healthz"
+        );
+        assert_eq!(
+            probe_url("https://my-app.cyndra.app", "api/health"),
+            "https://my-app.cyndra.app/api/health"
+        );
+        assert_eq!(
+            probe_url("https://my-app.cyndra.app", "/"),
+            "https://my-app.cyndra.app/"
+        );
+    }
+
+    #[tokio::test]
+    async fn probes_tcp() {
+        let timeout = Duration::from_secs(1);
+
+        // keeps the connection open
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move { listener.accept().await.unwrap() });
+        assert_eq!(probe_tcp(addr, timeout).await, Ok(()));
+        drop(server.await.unwrap());
+
+        // closes the connection right away, like Docker does when nothing listens in the container
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move { drop(listener.accept().await.unwrap()) });
+        assert!(probe_tcp(addr, timeout).await.is_err());
+        server.await.unwrap();
+
+        // nothing listening
+        assert!(probe_tcp(addr, timeout).await.is_err());
+    }
+}
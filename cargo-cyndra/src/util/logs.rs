@@ -0,0 +1,250 @@
+use std::{collections::HashSet, str::FromStr};
+
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use cyndra_common::models::log::LogItem;
+use regex::Regex;
+use tracing::Level;
+
+/// Parse a point in time given as an RFC 3339 timestamp (`2024-05-01T12:00:00Z`)
+/// or as a duration before now (`30s`, `15m`, `2h`, `1d`).
+pub fn parse_timestamp(s: &str) -> Result<DateTime<Utc>> {
+    if let Ok(t) = DateTime::parse_from_rfc3339(s) {
+        return Ok(t.with_timezone(&Utc));
+    }
+
+    let Some(unit) = s.chars().last() else {
+        bail!("empty timestamp");
+    };
+    let amount: i64 = s[..s.len() - unit.len_utf8()]
+        .parse()
+        .with_context(|| format!("'{s}' is neither an RFC 3339 timestamp nor a duration"))?;
+    let ago = match unit {
+        's' => Duration::seconds(amount),
+        'm' => Duration::minutes(amount),
+        'h' => Duration::hours(amount),
+        'd' => Duration::days(amount),
+        _ => bail!("unknown duration unit '{unit}' in '{s}', use one of s, m, h, d"),
+    };
+
+    Ok(Utc::now() - ago)
+}
+
+/// Which log lines to show
+#[derive(Debug, Default)]
+pub struct LogFilter {
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    /// Only lines from these sources, or all sources if empty
+    pub sources: Vec<String>,
+    pub grep: Option<Regex>,
+    /// Only lines at this level or more severe
+    pub level: Option<Level>,
+}
+
+impl LogFilter {
+    pub fn matches(&self, log: &LogItem) -> bool {
+        self.since.is_none_or(|since| log.timestamp >= since)
+            && self.until.is_none_or(|until| log.timestamp <= until)
+            && (self.sources.is_empty() || self.sources.contains(&log.source))
+            && self.grep.as_ref().is_none_or(|re| re.is_match(&log.line))
+            // tracing orders levels by verbosity, so more severe levels are smaller
+            && self
+                .level
+                .is_none_or(|level| log_level(&log.line).is_some_and(|l| l <= level))
+    }
+}
+
+/// Find the level of a line printed by a tracing subscriber.
+/// Supports JSON output, which has the level in a `level` or OpenTelemetry `severity_text` field,
+/// and the text output of the fmt layer, which has it as one of the first words.
+pub fn log_level(line: &str) -> Option<Level> {
+    let line = strip_ansi(line.trim());
+
+    if line.starts_with('{') {
+        let json = serde_json::from_str::<serde_json::Value>(&line).ok()?;
+        let level = ["level", "severity_text", "severityText"]
+            .iter()
+            .find_map(|key| json.get(key)?.as_str())?;
+        return parse_level(level);
+    }
+
+    // the level can be preceded by a timestamp
+    line.split_whitespace().take(3).find_map(parse_level)
+}
+
+fn parse_level(s: &str) -> Option<Level> {
+    match s.to_ascii_uppercase().as_str() {
+        "WARNING" => Some(Level::WARN),
+        "FATAL" | "CRITICAL" => Some(Level::ERROR),
+        level => Level::from_str(level).ok(),
+    }
+}
+
+/// Remove the color codes that the fmt layer adds when ANSI output is on
+fn strip_ansi(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // skip until the final letter of the escape sequence
+            chars.by_ref().find(|c| c.is_ascii_alphabetic());
+        } else {
+            out.push(c);
+        }
+    }
+
+    out
+}
+
+/// Keep the first `head` or last `tail` lines
+pub fn select_range(mut logs: Vec<LogItem>, head: Option<u32>, tail: Option<u32>) -> Vec<LogItem> {
+    if let Some(head) = head {
+        logs.truncate(head as usize);
+    }
+    if let Some(tail) = tail {
+        let skip = logs.len().saturating_sub(tail as usize);
+        logs.drain(..skip);
+    }
+
+    logs
+}
+
+/// Drops log lines that were already seen, for when a log stream is resumed after reconnecting.
+///
+/// Lines are assumed to arrive in timestamp order, so only the lines at the latest timestamp
+/// need to be remembered to tell duplicates apart from distinct lines with the same timestamp.
+#[derive(Debug, Default)]
+pub struct LogDeduplicator {
+    last_timestamp: Option<DateTime<Utc>>,
+    seen_at_last_timestamp: HashSet<(String, String)>,
+}
+
+impl LogDeduplicator {
+    /// Returns true if the line has not been seen before, and remembers it
+    pub fn is_new(&mut self, log: &LogItem) -> bool {
+        let key = (log.source.clone(), log.line.clone());
+        match self.last_timestamp {
+            Some(last) if log.timestamp < last => false,
+            Some(last) if log.timestamp == last => self.seen_at_last_timestamp.insert(key),
+            _ => {
+                self.last_timestamp = Some(log.timestamp);
+                self.seen_at_last_timestamp.clear();
+                self.seen_at_last_timestamp.insert(key);
+                true
+            }
+        }
+    }
+
+    /// Timestamp of the most recent line seen, to resume a stream from
+    pub fn last_timestamp(&self) -> Option<DateTime<Utc>> {
+        self.last_timestamp
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn log(secs: i64, line: &str) -> LogItem {
+        LogItem::new(
+            DateTime::from_timestamp(secs, 0).unwrap(),
+            "app".to_owned(),
+            line.to_owned(),
+        )
+    }
+
+    fn seconds(logs: &[LogItem]) -> Vec<i64> {
+        logs.iter().map(|l| l.timestamp.timestamp()).collect()
+    }
+
+    #[test]
+    fn parses_timestamps() {
+        assert_eq!(
+            parse_timestamp("2024-05-01T12:00:00+02:00").unwrap(),
+            DateTime::parse_from_rfc3339("2024-05-01T10:00:00Z").unwrap()
+        );
+
+        let since = parse_timestamp("15m").unwrap();
+        let ago = Utc::now() - since;
+        assert!(ago >= Duration::minutes(15) && ago < Duration::minutes(16));
+
+        assert!(parse_timestamp("").is_err());
+        assert!(parse_timestamp("15").is_err());
+        assert!(parse_timestamp("15w").is_err());
+        assert!(parse_timestamp("yesterday").is_err());
+    }
+
+    #[test]
+    fn selects_range() {
+        let logs = (0..5).map(|i| log(i, "")).collect::<Vec<_>>();
+
+        let head = select_range(logs.clone(), Some(2), None);
+        assert_eq!(seconds(&head), [0, 1]);
+
+        let tail = select_range(logs.clone(), None, Some(2));
+        assert_eq!(seconds(&tail), [3, 4]);
+
+        assert_eq!(select_range(logs, None, Some(10)).len(), 5);
+    }
+
+    #[test]
+    fn finds_log_levels() {
+        assert_eq!(
+            log_level(
+                r#"{"timestamp":"2024-05-01T12:00:00Z","level":"WARN","fields":{"message":"slow"}}"#
+            ),
+            Some(Level::WARN)
+        );
+        assert_eq!(
+            log_level(r#"{"severity_text":"ERROR","body":"failed"}"#),
+            Some(Level::ERROR)
+        );
+        assert_eq!(log_level(" INFO my_app: started"), Some(Level::INFO));
+        assert_eq!(
+            log_level("\x1b[2m2024-05-01T12:00:00Z\x1b[0m \x1b[31mERROR\x1b[0m my_app: failed"),
+            Some(Level::ERROR)
+        );
+        assert_eq!(log_level("Compiling my_app v0.1.0"), None);
+        assert_eq!(log_level(r#"{"message":"no level"}"#), None);
+    }
+
+    #[test]
+    fn filters_logs() {
+        let filter = LogFilter {
+            since: Some(DateTime::from_timestamp(1, 0).unwrap()),
+            until: Some(DateTime::from_timestamp(3, 0).unwrap()),
+            sources: vec!["app".to_owned()],
+            grep: Some(Regex::new("^db").unwrap()),
+            level: Some(Level::WARN),
+        };
+
+        assert!(filter.matches(&log(2, "db WARN pool exhausted")));
+        assert!(filter.matches(&log(3, "db ERROR connection lost")));
+        assert!(!filter.matches(&log(0, "db WARN pool exhausted")));
+        assert!(!filter.matches(&log(4, "db WARN pool exhausted")));
+        assert!(!filter.matches(&log(2, "db INFO connected")));
+        assert!(!filter.matches(&log(2, "http WARN slow request")));
+
+        let mut build = log(2, "db WARN pool exhausted");
+        build.source = "build".to_owned();
+        assert!(!filter.matches(&build));
+    }
+
+    #[test]
+    fn deduplicates_resumed_stream() {
+        let mut dedup = LogDeduplicator::default();
+
+        assert!(dedup.is_new(&log(1, "a")));
+        assert!(dedup.is_new(&log(2, "b")));
+        assert!(dedup.is_new(&log(2, "c")));
+        assert_eq!(dedup.last_timestamp().unwrap().timestamp(), 2);
+
+        // stream resumed from the last timestamp
+        assert!(!dedup.is_new(&log(1, "a")));
+        assert!(!dedup.is_new(&log(2, "b")));
+        assert!(!dedup.is_new(&log(2, "c")));
+        assert!(dedup.is_new(&log(2, "d")));
+        assert!(dedup.is_new(&log(3, "b")));
+    }
+}
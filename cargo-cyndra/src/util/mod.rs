@@ -1,4 +1,10 @@
+pub mod archive;
 pub mod bacon;
+pub mod deployments;
+pub mod docker;
+pub mod health;
+pub mod logs;
+pub mod secrets;
 
 use std::{
     fmt::Write,
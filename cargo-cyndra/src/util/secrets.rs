@@ -0,0 +1,277 @@
+use std::{
+    collections::HashMap,
+    io::{self, IsTerminal, Read},
+    path::Path,
+    time::Duration,
+};
+
+use anyhow::{bail, Context, Result};
+use dialoguer::{theme::ColorfulTheme, Password};
+use serde::Serialize;
+use tokio::process::Command;
+
+pub const DEFAULT_ENV_PREFIX: &str = "cyndra_SECRET_";
+
+/// How long the secrets command may run before it is killed
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Parse the `KEY=value` lines of a .env file.
+/// Supports comments, blank lines, `export` prefixes, and single or double quoted values.
+pub fn parse_dotenv(content: &str) -> Result<HashMap<String, String>> {
+    let mut secrets = HashMap::new();
+
+    for (i, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let Some((key, value)) = line.split_once('=') else {
+            bail!("line {} is not of the form KEY=value", i + 1);
+        };
+        let value = value.trim();
+        let value = match (value.chars().next(), value.chars().last()) {
+            (Some(q @ ('"' | '\'')), Some(end)) if value.len() >= 2 && q == end => {
+                &value[1..value.len() - 1]
+            }
+            // unquoted values can have trailing comments
+            _ => value.split(" #").next().unwrap_or_default().trim_end(),
+        };
+
+        secrets.insert(key.trim().to_owned(), value.to_owned());
+    }
+
+    Ok(secrets)
+}
+
+/// Take the variables that start with `prefix`, with the prefix stripped from their names
+pub fn env_secrets(
+    vars: impl IntoIterator<Item = (String, String)>,
+    prefix: &str,
+) -> HashMap<String, String> {
+    vars.into_iter()
+        .filter_map(|(key, value)| {
+            key.strip_prefix(prefix)
+                .filter(|key| !key.is_empty())
+                .map(|key| (key.to_owned(), value))
+        })
+        .collect()
+}
+
+/// Run a command in `working_directory` and parse its output as a JSON object of secrets
+pub async fn command_secrets(
+    command: &[String],
+    working_directory: &Path,
+) -> Result<HashMap<String, String>> {
+    command_secrets_with_timeout(command, working_directory, COMMAND_TIMEOUT).await
+}
+
+async fn command_secrets_with_timeout(
+    command: &[String],
+    working_directory: &Path,
+    timeout: Duration,
+) -> Result<HashMap<String, String>> {
+    let Some((program, args)) = command.split_first() else {
+        bail!("The secrets command in Cyndra.toml is empty");
+    };
+
+    let output = Command::new(program)
+        .args(args)
+        .current_dir(working_directory)
+        .kill_on_drop(true)
+        .output();
+    let output = tokio::time::timeout(timeout, output)
+        .await
+        .with_context(|| {
+            format!(
+                "Secrets command `{program}` did not finish within {} seconds",
+                timeout.as_secs()
+            )
+        })?
+        .with_context(|| format!("Failed to run secrets command `{program}`"))?;
+    if !output.status.success() {
+        bail!(
+            "Secrets command `{program}` exited with code {}: {}",
+            output.status.code().unwrap_or_default(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+    }
+
+    serde_json::from_slice(&output.stdout).with_context(|| {
+        format!("Secrets command `{program}` did not print a JSON object of strings")
+    })
+}
+
+/// Read the value of the secret `key` from `from_file`, or else from a prompt or piped stdin,
+/// so that it does not have to be passed on the command line
+pub fn read_secret_value(key: &str, from_file: Option<&Path>) -> Result<String> {
+    let value = if let Some(path) = from_file {
+        std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read the value of {key} from {}", path.display()))?
+    } else if io::stdin().is_terminal() {
+        return Password::with_theme(&ColorfulTheme::default())
+            .with_prompt(format!("Value of {key}"))
+            .allow_empty_password(true)
+            .interact()
+            .with_context(|| format!("Failed to read the value of {key}"));
+    } else {
+        let mut value = String::new();
+        io::stdin()
+            .read_to_string(&mut value)
+            .with_context(|| format!("Failed to read the value of {key} from stdin"))?;
+        value
+    };
+
+    Ok(strip_trailing_newline(value))
+}
+
+/// Drop the single line ending that files and `echo` leave after a value
+fn strip_trailing_newline(mut value: String) -> String {
+    if value.ends_with('\n') {
+        value.pop();
+        if value.ends_with('\r') {
+            value.pop();
+        }
+    }
+    value
+}
+
+/// Keys that differ between local and remote secrets
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct SecretsDiff {
+    /// Only in the local secrets
+    pub added: Vec<String>,
+    /// Only in the remote secrets
+    pub removed: Vec<String>,
+    /// In both, but with different values
+    pub changed: Vec<String>,
+}
+
+impl SecretsDiff {
+    pub fn new(local: &HashMap<String, String>, remote: &HashMap<String, String>) -> Self {
+        let mut diff = Self::default();
+        for (key, value) in local {
+            match remote.get(key) {
+                None => diff.added.push(key.clone()),
+                Some(v) if v != value => diff.changed.push(key.clone()),
+                Some(_) => {}
+            }
+        }
+        diff.removed = remote
+            .keys()
+            .filter(|key| !local.contains_key(*key))
+            .cloned()
+            .collect();
+
+        diff.added.sort();
+        diff.removed.sort();
+        diff.changed.sort();
+
+        diff
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dotenv() {
+        let secrets = parse_dotenv(
+            r#"
+# a comment
+KEY=value
+export EXPORTED=1
+DOUBLE="quoted # not a comment"
+SINGLE='single'
+TRAILING=value # a comment
+EMPTY=
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(
+            secrets,
+            HashMap::from([
+                ("KEY".to_owned(), "value".to_owned()),
+                ("EXPORTED".to_owned(), "1".to_owned()),
+                ("DOUBLE".to_owned(), "quoted # not a comment".to_owned()),
+                ("SINGLE".to_owned(), "single".to_owned()),
+                ("TRAILING".to_owned(), "value".to_owned()),
+                ("EMPTY".to_owned(), "".to_owned()),
+            ])
+        );
+        assert!(parse_dotenv("NOT A SECRET").is_err());
+    }
+
+    #[test]
+    fn strips_one_trailing_newline() {
+        assert_eq!(strip_trailing_newline("value\n".to_owned()), "value");
+        assert_eq!(strip_trailing_newline("value\r\n".to_owned()), "value");
+        assert_eq!(strip_trailing_newline("value\n\n".to_owned()), "value\n");
+        assert_eq!(strip_trailing_newline(" value ".to_owned()), " value ");
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn command() {
+        let command = |script: &str| ["sh".to_owned(), "-c".to_owned(), script.to_owned()];
+        let dir = std::env::temp_dir();
+
+        assert_eq!(
+            command_secrets(&command(r#"echo '{"KEY": "value"}'"#), &dir)
+                .await
+                .unwrap(),
+            HashMap::from([("KEY".to_owned(), "value".to_owned())])
+        );
+        assert!(command_secrets(&command("exit 1"), &dir).await.is_err());
+
+        let error =
+            command_secrets_with_timeout(&command("sleep 10"), &dir, Duration::from_millis(100))
+                .await
+                .unwrap_err();
+        assert!(error.to_string().contains("did not finish"));
+    }
+
+    #[test]
+    fn diff() {
+        let local = HashMap::from([
+            ("SAME".to_owned(), "1".to_owned()),
+            ("CHANGED".to_owned(), "new".to_owned()),
+            ("ADDED".to_owned(), "1".to_owned()),
+        ]);
+        let remote = HashMap::from([
+            ("SAME".to_owned(), "1".to_owned()),
+            ("CHANGED".to_owned(), "old".to_owned()),
+            ("REMOVED".to_owned(), "1".to_owned()),
+        ]);
+
+        assert_eq!(
+            SecretsDiff::new(&local, &remote),
+            SecretsDiff {
+                added: vec!["ADDED".to_owned()],
+                removed: vec!["REMOVED".to_owned()],
+                changed: vec!["CHANGED".to_owned()],
+            }
+        );
+        assert!(SecretsDiff::new(&local, &local).is_empty());
+    }
+
+    #[test]
+    fn env() {
+        let vars = [
+            ("cyndra_SECRET_API_KEY".to_owned(), "abc".to_owned()),
+            ("cyndra_SECRET_".to_owned(), "no name".to_owned()),
+            ("PATH".to_owned(), "/bin".to_owned()),
+        ];
+
+        assert_eq!(
+            env_secrets(vars, DEFAULT_ENV_PREFIX),
+            HashMap::from([("API_KEY".to_owned(), "abc".to_owned())])
+        );
+    }
+}
@@ -7,7 +7,7 @@ use cargo_cyndra::builder::{build_workspace, BuiltService};
 async fn not_cyndra() {
     let (tx, _) = tokio::sync::mpsc::channel::<String>(256);
     let project_path = format!("{}/tests/resources/not-cyndra", env!("CARGO_MANIFEST_DIR"));
-    build_workspace(Path::new(&project_path), false, tx, false)
+    build_workspace(Path::new(&project_path), false, tx, false, None)
         .await
         .unwrap();
 }
@@ -19,7 +19,7 @@ async fn not_cyndra() {
 async fn not_bin() {
     let (tx, _) = tokio::sync::mpsc::channel::<String>(256);
     let project_path = format!("{}/tests/resources/not-bin", env!("CARGO_MANIFEST_DIR"));
-    match build_workspace(Path::new(&project_path), false, tx, false).await {
+    match build_workspace(Path::new(&project_path), false, tx, false, None).await {
         Ok(_) => {}
         Err(e) => panic!("{}", e.to_string()),
     }
@@ -31,7 +31,7 @@ async fn is_bin() {
     let project_path = format!("{}/tests/resources/is-bin", env!("CARGO_MANIFEST_DIR"));
 
     assert_eq!(
-        build_workspace(Path::new(&project_path), false, tx, false)
+        build_workspace(Path::new(&project_path), false, tx, false, None)
             .await
             .unwrap(),
         vec![BuiltService {
@@ -51,7 +51,7 @@ async fn not_found() {
         "{}/tests/resources/non-existing",
         env!("CARGO_MANIFEST_DIR")
     );
-    build_workspace(Path::new(&project_path), false, tx, false)
+    build_workspace(Path::new(&project_path), false, tx, false, None)
         .await
         .unwrap();
 }
@@ -68,7 +68,7 @@ async fn workspace() {
     let project_path = format!("{}/tests/resources/workspace", env!("CARGO_MANIFEST_DIR"));
 
     assert_eq!(
-        build_workspace(Path::new(&project_path), false, tx, false)
+        build_workspace(Path::new(&project_path), false, tx, false, None)
             .await
             .unwrap(),
         vec![
@@ -87,3 +87,37 @@ async fn workspace() {
         ]
     );
 }
+
+// Test that a single package can be picked out of a workspace
+#[tokio::test]
+async fn workspace_package() {
+    let (tx, mut rx) = tokio::sync::mpsc::channel::<String>(256);
+    tokio::spawn(async move {
+        while let Some(l) = rx.recv().await {
+            println!("{l}");
+        }
+    });
+    let project_path = format!("{}/tests/resources/workspace", env!("CARGO_MANIFEST_DIR"));
+
+    assert_eq!(
+        build_workspace(Path::new(&project_path), false, tx, false, Some("alpha2"))
+            .await
+            .unwrap(),
+        vec![BuiltService {
+            workspace_path: PathBuf::from(&project_path),
+            manifest_path: PathBuf::from(&project_path).join("alpha2/Cargo.toml"),
+            package_name: "alpha2".to_string(),
+            executable_path: PathBuf::from(&project_path).join("target/debug/alpha2"),
+        }]
+    );
+}
+
+#[tokio::test]
+#[should_panic(expected = "Package 'shared' is not a Cyndra package in this workspace")]
+async fn workspace_package_not_cyndra() {
+    let (tx, _) = tokio::sync::mpsc::channel::<String>(256);
+    let project_path = format!("{}/tests/resources/workspace", env!("CARGO_MANIFEST_DIR"));
+    build_workspace(Path::new(&project_path), false, tx, false, Some("shared"))
+        .await
+        .unwrap();
+}
@@ -2,7 +2,7 @@ mod builder;
 mod init;
 mod run;
 
-use cargo_cyndra::{Command, ProjectArgs, Cyndra, CyndraArgs};
+use cargo_cyndra::{Command, OutputMode, ProjectArgs, Cyndra, CyndraArgs};
 use std::path::Path;
 
 /// Creates a CLI instance with some reasonable defaults set
@@ -22,6 +22,7 @@ async fn cyndra_command(cmd: Command, working_directory: &str) -> anyhow::Result
                 },
                 offline: false,
                 debug: false,
+                output_mode: OutputMode::Normal,
                 cmd,
             },
             false,
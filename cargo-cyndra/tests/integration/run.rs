@@ -1,6 +1,6 @@
 use std::{fs::canonicalize, process::exit, time::Duration};
 
-use cargo_cyndra::{Command, ProjectArgs, RunArgs, Cyndra, CyndraArgs};
+use cargo_cyndra::{Command, OutputMode, ProjectArgs, RunArgs, Cyndra, CyndraArgs};
 use portpicker::pick_unused_port;
 use tokio::time::sleep;
 
@@ -40,12 +40,14 @@ async fn cyndra_run(working_directory: &str, external: bool) -> String {
                 },
                 offline: false,
                 debug: false,
+                output_mode: OutputMode::Normal,
                 cmd: Command::Run(RunArgs {
                     port,
                     external,
                     release: false,
                     raw: false,
                     bacon: false,
+                    package: None,
                     secret_args: Default::default(),
                 }),
             },
@@ -18,6 +18,18 @@ pub struct DbInput {
     pub local_uri: Option<String>,
     /// Override the default db name. Only applies to RDS.
     pub db_name: Option<String>,
+    /// Image of the local database container, like "docker.io/library/postgres:15"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub local_image: Option<String>,
+    /// Extra environment variables of the local database container, as "KEY=value" strings
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub local_env: Vec<String>,
+    /// SQL files to run when the local database is first created, relative to the project
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub local_init_sql: Vec<String>,
+    /// Postgres extensions to create in the local database, like "vector" or "postgis"
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub local_extensions: Vec<String>,
 }
 
 /// The output produced by Cyndra DB resources
@@ -82,10 +94,26 @@ impl DatabaseInfo {
         )
     }
 
+    pub fn engine(&self) -> String {
+        self.engine.to_string()
+    }
+
     pub fn role_name(&self) -> String {
         self.role_name.to_string()
     }
 
+    pub fn role_password(&self) -> String {
+        self.role_password.to_string()
+    }
+
+    pub fn hostname(&self) -> String {
+        self.hostname.to_string()
+    }
+
+    pub fn port(&self) -> String {
+        self.port.to_string()
+    }
+
     pub fn database_name(&self) -> String {
         self.database_name.to_string()
     }
@@ -114,6 +142,34 @@ pub struct ContainerRequest {
     pub port: String,
     /// list of "KEY=value" strings
     pub env: Vec<String>,
+    /// How to check that the container is ready to use. Not checked if `None`.
+    #[serde(default)]
+    pub ready_check: Option<ContainerReadyCheck>,
+    /// How long to wait for the ready check to pass, in seconds. Defaults to 60.
+    #[serde(default)]
+    pub ready_timeout_secs: Option<u64>,
+}
+
+/// How the local run provisioner checks that a container is ready to use
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContainerReadyCheck {
+    /// Run a command in the container until it exits successfully
+    Command(Vec<String>),
+    /// Connect to the exposed port until something accepts the connection
+    Tcp,
+    /// Request a path on the exposed port until it responds with a success status
+    Http { path: String },
+}
+
+impl std::fmt::Display for ContainerReadyCheck {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Command(cmd) => write!(f, "command `{}`", cmd.join(" ")),
+            Self::Tcp => write!(f, "TCP connection"),
+            Self::Http { path } => write!(f, "HTTP request to {path}"),
+        }
+    }
 }
 
 /// Response from requesting a container from the local run provisioner
@@ -140,6 +196,8 @@ pub fn semvers_are_compatible(a: &semver::Version, b: &semver::Version) -> bool
 mod tests {
     use std::str::FromStr;
 
+    use super::*;
+
     #[test]
     fn semver_compatibility_check_works() {
         let semver_tests = &[
@@ -157,4 +215,33 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn container_request_ready_check_is_optional() {
+        let request: ContainerRequest = serde_json::from_value(serde_json::json!({
+            "project_name": "my-app",
+            "container_name": "qdrant",
+            "image": "docker.io/qdrant/qdrant:v1.10.0",
+            "port": "6334/tcp",
+            "env": [],
+        }))
+        .unwrap();
+        assert!(request.ready_check.is_none());
+
+        let request: ContainerRequest = serde_json::from_value(serde_json::json!({
+            "project_name": "my-app",
+            "container_name": "web",
+            "image": "nginx",
+            "port": "80/tcp",
+            "env": [],
+            "ready_check": { "http": { "path": "/" } },
+            "ready_timeout_secs": 10,
+        }))
+        .unwrap();
+        assert!(matches!(
+            request.ready_check,
+            Some(ContainerReadyCheck::Http { path }) if path == "/"
+        ));
+        assert_eq!(request.ready_timeout_secs, Some(10));
+    }
 }
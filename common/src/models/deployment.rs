@@ -92,6 +92,9 @@ pub struct DeploymentResponse {
     pub uris: Vec<String>,
     pub build_id: Option<String>,
     pub build_meta: Option<BuildMeta>,
+    /// The build args this deployment was built with, if it was built from source.
+    /// Kept as JSON, since it can have build args that this version does not know.
+    pub build_args: Option<serde_json::Value>,
 }
 
 #[cfg(feature = "display")]
@@ -130,7 +133,8 @@ pub struct UploadArchiveResponse {
 pub enum DeploymentRequest {
     /// Build an image from the source code in an attached zip archive
     BuildArchive(DeploymentRequestBuildArchive),
-    // TODO?: Add GitRepo(DeploymentRequestGitRepo)
+    /// Build an image from the source code at a commit in a git repository
+    GitRepo(DeploymentRequestGitRepo),
     /// Use this image directly. Can be used to skip the build step.
     Image(DeploymentRequestImage),
     //
@@ -150,7 +154,24 @@ pub struct DeploymentRequestBuildArchive {
     pub build_meta: Option<BuildMeta>,
 }
 
-#[derive(Deserialize, Serialize)]
+#[derive(Default, Deserialize, Serialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[typeshare::typeshare]
+pub struct DeploymentRequestGitRepo {
+    /// URL of the repository to clone
+    pub repo_url: String,
+    /// The branch, tag or commit to check out
+    pub git_ref: String,
+    /// Path of the project inside the repository, if not at its root
+    pub subdirectory: Option<String>,
+    pub build_args: Option<BuildArgs>,
+    /// Secrets to add before this deployment.
+    /// TODO: Remove this in favour of a separate secrets uploading action.
+    pub secrets: Option<HashMap<String, String>>,
+    pub build_meta: Option<BuildMeta>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
 #[serde(tag = "type", content = "content")]
 #[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
 #[typeshare::typeshare]
@@ -160,7 +181,7 @@ pub enum BuildArgs {
     // No Unknown variant: is a Request type and should only be deserialized on backend
 }
 
-#[derive(Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
 #[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
 #[typeshare::typeshare]
 pub struct BuildArgsRust {
@@ -5,6 +5,7 @@ pub mod error;
 pub mod log;
 pub mod project;
 pub mod resource;
+pub mod secret;
 pub mod team;
 pub mod telemetry;
 pub mod user;
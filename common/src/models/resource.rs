@@ -91,6 +91,9 @@ pub enum ResourceType {
     #[strum(to_string = "database::aws_rds::mariadb")]
     #[serde(rename = "database::aws_rds::mariadb")]
     DatabaseAwsRdsMariaDB,
+    #[strum(to_string = "cache::redis")]
+    #[serde(rename = "cache::redis")]
+    CacheRedis,
     /// (Will probably be removed)
     #[strum(to_string = "secrets")]
     #[serde(rename = "secrets")]
@@ -119,6 +122,7 @@ mod tests {
     fn to_string_and_back() {
         let inputs = [
             ResourceType::DatabaseSharedPostgres,
+            ResourceType::CacheRedis,
             ResourceType::Secrets,
             ResourceType::Container,
         ];
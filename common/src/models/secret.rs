@@ -0,0 +1,27 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+// No Debug derive, to not leak the values
+#[derive(Deserialize, Serialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[typeshare::typeshare]
+pub struct SecretsSetRequest {
+    /// Secrets to add or overwrite. Other secrets are kept.
+    pub secrets: BTreeMap<String, String>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[typeshare::typeshare]
+pub struct SecretsUnsetRequest {
+    pub keys: Vec<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[typeshare::typeshare]
+pub struct SecretKeysResponse {
+    /// The keys of all secrets in the project after the change
+    pub keys: Vec<String>,
+}
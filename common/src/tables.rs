@@ -10,11 +10,29 @@ use crate::{
         deployment::DeploymentResponse,
         project::ProjectResponse,
         resource::{ResourceResponse, ResourceType},
+        team::{TeamMembersResponse, TeamResponse},
     },
     secrets::SecretStore,
     DatabaseInfo,
 };
 
+pub fn get_secrets_list_table(secrets: &SecretStore, raw: bool) -> String {
+    let mut table = Table::new();
+    table
+        .load_preset(if raw { NOTHING } else { UTF8_BORDERS_ONLY })
+        .set_content_arrangement(ContentArrangement::Disabled)
+        .set_header(vec!["Key", "Value"]);
+
+    for (key, secret) in secrets.secrets.iter() {
+        table.add_row(vec![
+            Cell::new(key).add_attribute(Attribute::Bold),
+            Cell::new(secret.redacted()),
+        ]);
+    }
+
+    table.to_string()
+}
+
 pub fn get_certificates_table(certs: &[CertificateResponse], raw: bool) -> String {
     let mut table = Table::new();
     table
@@ -59,6 +77,62 @@ pub fn deployments_table(deployments: &[DeploymentResponse], raw: bool) -> Strin
     table.to_string()
 }
 
+pub fn get_teams_table(teams: &[TeamResponse], raw: bool) -> String {
+    let mut table = Table::new();
+    table
+        .load_preset(if raw { NOTHING } else { UTF8_BORDERS_ONLY })
+        .set_content_arrangement(ContentArrangement::Disabled)
+        .set_header(vec!["Team ID", "Team Name", "Your Role"]);
+
+    for team in teams {
+        table.add_row(vec![
+            Cell::new(&team.id).add_attribute(Attribute::Bold),
+            Cell::new(&team.name),
+            Cell::new(&team.membership.role),
+        ]);
+    }
+
+    table.to_string()
+}
+
+pub fn get_team_members_tables(members: &TeamMembersResponse, raw: bool) -> String {
+    let mut table = Table::new();
+    table
+        .load_preset(if raw { NOTHING } else { UTF8_BORDERS_ONLY })
+        .set_content_arrangement(ContentArrangement::Disabled)
+        .set_header(vec!["User ID", "Name", "Email", "Role"]);
+
+    for member in &members.members {
+        table.add_row(vec![
+            Cell::new(&member.user_id).add_attribute(Attribute::Bold),
+            Cell::new(member.nickname.as_deref().unwrap_or_default()),
+            Cell::new(member.email.as_deref().unwrap_or_default()),
+            Cell::new(&member.role),
+        ]);
+    }
+
+    if members.invites.is_empty() {
+        return table.to_string();
+    }
+
+    let mut invites = Table::new();
+    invites
+        .load_preset(if raw { NOTHING } else { UTF8_BORDERS_ONLY })
+        .set_content_arrangement(ContentArrangement::Disabled)
+        .set_header(vec!["Invited Email", "Role", "Expires"]);
+
+    for invite in &members.invites {
+        let datetime: DateTime<Local> = DateTime::from(invite.expires_at);
+        invites.add_row(vec![
+            Cell::new(&invite.email),
+            Cell::new(&invite.role),
+            Cell::new(datetime.to_rfc3339_opts(SecondsFormat::Secs, false)),
+        ]);
+    }
+
+    format!("{table}\n\nPending invites:\n{invites}")
+}
+
 pub fn get_projects_table(projects: &[ProjectResponse], raw: bool) -> String {
     let mut table = Table::new();
     table
@@ -116,6 +190,7 @@ pub fn get_resource_tables(
                         | ResourceType::DatabaseAwsRdsMariaDB
                         | ResourceType::DatabaseAwsRdsMySql
                         | ResourceType::DatabaseAwsRdsPostgres
+                        | ResourceType::CacheRedis
                 )
             })
             .map(Clone::clone)
@@ -136,6 +136,11 @@ export interface DeploymentResponse {
 	uris: string[];
 	build_id?: string;
 	build_meta?: BuildMeta;
+	/**
+	 * The build args this deployment was built with, if it was built from source.
+	 * Kept as JSON, since it can have build args that this version does not know.
+	 */
+	build_args?: any;
 }
 
 export interface DeploymentListResponse {
@@ -157,6 +162,22 @@ export interface DeploymentRequestBuildArchive {
 	build_meta?: BuildMeta;
 }
 
+export interface DeploymentRequestGitRepo {
+	/** URL of the repository to clone */
+	repo_url: string;
+	/** The branch, tag or commit to check out */
+	git_ref: string;
+	/** Path of the project inside the repository, if not at its root */
+	subdirectory?: string;
+	build_args?: BuildArgs;
+	/**
+	 * Secrets to add before this deployment.
+	 * TODO: Remove this in favour of a separate secrets uploading action.
+	 */
+	secrets?: Record<string, string>;
+	build_meta?: BuildMeta;
+}
+
 export interface DeploymentRequestImage {
 	image: string;
 	/** TODO: Remove this in favour of a separate secrets uploading action. */
@@ -261,6 +282,7 @@ export enum ResourceType {
 	DatabaseAwsRdsPostgres = "database::aws_rds::postgres",
 	DatabaseAwsRdsMySql = "database::aws_rds::mysql",
 	DatabaseAwsRdsMariaDB = "database::aws_rds::mariadb",
+	CacheRedis = "cache::redis",
 	/** (Will probably be removed) */
 	Secrets = "secrets",
 	/** Local provisioner only */
@@ -300,6 +322,20 @@ export interface ResourceListResponse {
 	resources: ResourceResponse[];
 }
 
+export interface SecretKeysResponse {
+	/** The keys of all secrets in the project after the change */
+	keys: string[];
+}
+
+export interface SecretsSetRequest {
+	/** Secrets to add or overwrite. Other secrets are kept. */
+	secrets: Record<string, string>;
+}
+
+export interface SecretsUnsetRequest {
+	keys: string[];
+}
+
 export enum SubscriptionType {
 	Pro = "pro",
 	Rds = "rds",
@@ -412,6 +448,8 @@ export interface UserUsageResponse {
 export type DeploymentRequest = 
 	/** Build an image from the source code in an attached zip archive */
 	| { type: "BuildArchive", content: DeploymentRequestBuildArchive }
+	/** Build an image from the source code at a commit in a git repository */
+	| { type: "GitRepo", content: DeploymentRequestGitRepo }
 	/** Use this image directly. Can be used to skip the build step. */
 	| { type: "Image", content: DeploymentRequestImage };
 
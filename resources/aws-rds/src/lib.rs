@@ -53,6 +53,44 @@ macro_rules! aws_engine {
 
                     self
                 }
+
+                /// Use this image for the local database container, like `postgres:15`.
+                /// The data of each image is kept separately.
+                pub fn local_image(mut self, local_image: &str) -> Self {
+                    self.0.local_image = Some(local_image.to_string());
+
+                    self
+                }
+
+                /// Extra environment variables of the local database container, as `KEY=value` strings
+                pub fn local_env(
+                    mut self,
+                    local_env: impl IntoIterator<Item = impl Into<String>>,
+                ) -> Self {
+                    self.0.local_env = local_env.into_iter().map(Into::into).collect();
+
+                    self
+                }
+
+                /// SQL files to run when the local database is first created, relative to the project
+                pub fn local_init_sql(
+                    mut self,
+                    local_init_sql: impl IntoIterator<Item = impl Into<String>>,
+                ) -> Self {
+                    self.0.local_init_sql = local_init_sql.into_iter().map(Into::into).collect();
+
+                    self
+                }
+
+                /// Postgres extensions to create in the local database, like `vector` or `postgis`
+                pub fn local_extensions(
+                    mut self,
+                    local_extensions: impl IntoIterator<Item = impl Into<String>>,
+                ) -> Self {
+                    self.0.local_extensions = local_extensions.into_iter().map(Into::into).collect();
+
+                    self
+                }
             }
 
             #[cfg(feature = $feature)]
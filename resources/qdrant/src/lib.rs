@@ -3,8 +3,8 @@ use serde::{Deserialize, Serialize};
 use cyndra_service::{
     error::{CustomError, Error},
     resource::{ProvisionResourceRequest, ResourceType},
-    ContainerRequest, ContainerResponse, Environment, IntoResource, ResourceFactory,
-    ResourceInputBuilder,
+    ContainerReadyCheck, ContainerRequest, ContainerResponse, Environment, IntoResource,
+    ResourceFactory, ResourceInputBuilder,
 };
 
 /// A Qdrant vector database
@@ -72,6 +72,8 @@ impl ResourceInputBuilder for Qdrant {
                         image: "docker.io/qdrant/qdrant:v1.10.0".to_string(),
                         port: "6334/tcp".to_string(),
                         env: vec![],
+                        ready_check: Some(ContainerReadyCheck::Tcp),
+                        ready_timeout_secs: None,
                     })
                     .unwrap(),
                 })),
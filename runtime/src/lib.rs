@@ -19,8 +19,8 @@ pub use async_trait::async_trait;
 pub use plugins::{Metadata, Secrets};
 pub use cyndra_codegen::main;
 pub use cyndra_service::{
-    CustomError, DbInput, DeploymentMetadata, Environment, Error, IntoResource, ResourceFactory,
-    ResourceInputBuilder, SecretStore, Service,
+    CustomError, DbInput, DeploymentMetadata, Environment, Error, HealthCheck, HealthChecked,
+    IntoResource, ResourceFactory, ResourceInputBuilder, SecretStore, Service, Shutdown,
 };
 pub use tokio;
 
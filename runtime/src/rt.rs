@@ -1,25 +1,33 @@
 use std::{
     collections::BTreeMap,
+    future::Future,
     iter::FromIterator,
     net::{IpAddr, Ipv4Addr, SocketAddr},
     process::exit,
+    sync::{Arc, OnceLock},
+    time::Duration,
 };
 
 use anyhow::Context;
-use http_body_util::Empty;
-use hyper::{body::Bytes, server::conn::http1, service::service_fn, Response};
+use http_body_util::Full;
+use hyper::{body::Bytes, server::conn::http1, service::service_fn, Response, StatusCode};
 use hyper_util::rt::TokioIo;
 use cyndra_api_client::CyndraApiClient;
 use cyndra_common::{
     models::resource::{ResourceInput, ResourceState, ResourceType},
     secrets::Secret,
 };
-use cyndra_service::{Environment, ResourceFactory, Service};
+use cyndra_service::{Environment, HealthCheck, ResourceFactory, Service, Shutdown};
 use tokio::net::TcpListener;
 use tracing::{debug, info, trace};
 
 use crate::__internals::{Loader, Runner};
 
+/// How long a service's health check may take before it counts as failed
+const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(5);
+/// How long a service gets to shut down gracefully, unless set with `cyndra_SHUTDOWN_TIMEOUT`
+const DEFAULT_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+
 struct RuntimeEnvVars {
     /// Are we running in a Cyndra deployment?
     cyndra: bool,
@@ -32,6 +40,8 @@ struct RuntimeEnvVars {
     port: u16,
     /// Optional port to open health check on
     healthz_port: Option<u16>,
+    /// How long to wait for the service to drain after a shutdown signal
+    shutdown_timeout: Duration,
     /// Where to reach the required Cyndra API endpoints (mainly for provisioning)
     api_url: String,
     /// Key for the API calls (if relevant)
@@ -62,6 +72,9 @@ impl RuntimeEnvVars {
             healthz_port: std::env::var("cyndra_HEALTHZ_PORT")
                 .map(|s| s.parse().expect("invalid healthz port"))
                 .ok(),
+            shutdown_timeout: std::env::var("cyndra_SHUTDOWN_TIMEOUT")
+                .map(|s| Duration::from_secs(s.parse().expect("invalid shutdown timeout")))
+                .unwrap_or(DEFAULT_SHUTDOWN_TIMEOUT),
             api_url: std::env::var("cyndra_API").expect("api url env var"),
             api_key: std::env::var("cyndra_API_KEY").ok(),
         }
@@ -79,6 +92,7 @@ pub async fn start(loader: impl Loader + Send + 'static, runner: impl Runner + S
         ip,
         port,
         healthz_port,
+        shutdown_timeout,
         api_url,
         api_key,
     } = RuntimeEnvVars::parse();
@@ -86,8 +100,12 @@ pub async fn start(loader: impl Loader + Send + 'static, runner: impl Runner + S
     let service_addr = SocketAddr::new(ip, port);
     let client = CyndraApiClient::new(api_url, api_key, None, None);
 
+    // set once the service is ready to bind
+    let health_check = Arc::new(OnceLock::<HealthCheck>::new());
+
     // start a health check server if requested
     if let Some(healthz_port) = healthz_port {
+        let health_check = health_check.clone();
         trace!("Starting health check server on port {healthz_port}");
         let addr = SocketAddr::new(Ipv4Addr::UNSPECIFIED.into(), healthz_port);
         tokio::spawn(async move {
@@ -103,18 +121,21 @@ pub async fn start(loader: impl Loader + Send + 'static, runner: impl Runner + S
                     exit(202);
                 };
                 let io = TokioIo::new(stream);
+                let health_check = health_check.clone();
 
                 tokio::task::spawn(async move {
                     if let Err(err) = http1::Builder::new()
                         .serve_connection(
                             io,
-                            service_fn(|_req| async move {
-                                trace!("Received health check");
-                                // TODO: A hook into the `Service` trait can be added here
-                                trace!("Responding to health check");
-                                Result::<Response<Empty<Bytes>>, hyper::Error>::Ok(Response::new(
-                                    Empty::new(),
-                                ))
+                            service_fn(|_req| {
+                                let health_check = health_check.get().cloned();
+                                async move {
+                                    trace!("Received health check");
+                                    let response =
+                                        health_response(health_check, HEALTH_CHECK_TIMEOUT).await;
+                                    trace!("Responding to health check");
+                                    Result::<Response<Full<Bytes>>, hyper::Error>::Ok(response)
+                                }
                             }),
                         )
                         .await
@@ -216,7 +237,11 @@ pub async fn start(loader: impl Loader + Send + 'static, runner: impl Runner + S
                     }
                 }
                 Err(e) => {
-                    eprintln!("ERROR: Runtime Provisioning phase failed: {e}");
+                    // the local provisioner puts what went wrong in the error message
+                    eprintln!(
+                        "ERROR: Runtime Provisioning phase failed to provision {}: {e}",
+                        cyndra_resource.r#type
+                    );
                     exit(131);
                 }
             };
@@ -249,7 +274,16 @@ pub async fn start(loader: impl Loader + Send + 'static, runner: impl Runner + S
     //
     info!("Starting service");
 
-    let service_bind = service.bind(service_addr);
+    if let Some(check) = service.health_check() {
+        let _ = health_check.set(check);
+    }
+
+    let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel::<()>();
+    let shutdown = Shutdown::new(async move {
+        let _ = shutdown_rx.await;
+    });
+    let service_bind = service.bind(service_addr, shutdown);
+    tokio::pin!(service_bind);
 
     #[cfg(target_family = "unix")]
     let interrupted = {
@@ -260,7 +294,7 @@ pub async fn start(loader: impl Loader + Send + 'static, runner: impl Runner + S
             tokio::signal::unix::signal(tokio::signal::unix::SignalKind::interrupt())
                 .expect("Can not get the SIGINT signal receptor");
         tokio::select! {
-            res = service_bind => {
+            res = &mut service_bind => {
                 if let Err(e) = res {
                     tracing::error!("Service encountered an error in `bind`: {e}");
                     exit(1);
@@ -291,7 +325,7 @@ pub async fn start(loader: impl Loader + Send + 'static, runner: impl Runner + S
         let mut ctrl_shutdown_notif = tokio::signal::windows::ctrl_shutdown()
             .expect("Can not get the CtrlShutdown signal receptor");
         tokio::select! {
-            res = service_bind => {
+            res = &mut service_bind => {
                 if let Err(e) = res {
                     tracing::error!("Service encountered an error in `bind`: {e}");
                     exit(1);
@@ -323,6 +357,178 @@ pub async fn start(loader: impl Loader + Send + 'static, runner: impl Runner + S
     };
 
     if interrupted {
+        shut_down(shutdown_tx, service_bind, shutdown_timeout).await;
         exit(10);
     }
 }
+
+/// Tell the service to shut down, and wait up to `timeout` for its `bind` to return.
+/// Returns whether it shut down in time.
+async fn shut_down(
+    shutdown_tx: tokio::sync::oneshot::Sender<()>,
+    service_bind: impl Future<Output = Result<(), cyndra_service::Error>>,
+    timeout: Duration,
+) -> bool {
+    let _ = shutdown_tx.send(());
+    match tokio::time::timeout(timeout, service_bind).await {
+        Ok(Ok(())) => debug!("Service shut down gracefully"),
+        Ok(Err(e)) => tracing::error!("Service encountered an error while shutting down: {e}"),
+        Err(_) => {
+            tracing::warn!(
+                "Service did not shut down within {}s. Exiting anyway...",
+                timeout.as_secs()
+            );
+            return false;
+        }
+    }
+
+    true
+}
+
+/// Run the service's health check (if any) and turn the outcome into a response.
+/// A failing, panicking, or slow check gives a 503 with the reason in a JSON body.
+async fn health_response(
+    health_check: Option<HealthCheck>,
+    timeout: Duration,
+) -> Response<Full<Bytes>> {
+    let Some(health_check) = health_check else {
+        return Response::new(Full::default());
+    };
+
+    // spawned to catch panics, and aborted if it is too slow so that hanging checks don't pile up
+    let mut check = tokio::spawn(health_check.check());
+    let reason = match tokio::time::timeout(timeout, &mut check).await {
+        Ok(Ok(Ok(()))) => return Response::new(Full::default()),
+        Ok(Ok(Err(reason))) => reason,
+        Ok(Err(_)) => "health check panicked".to_owned(),
+        Err(_) => {
+            check.abort();
+            format!(
+                "health check did not finish within {}s",
+                timeout.as_secs_f32()
+            )
+        }
+    };
+    debug!("Health check failed: {reason}");
+
+    let body = serde_json::json!({ "status": "unhealthy", "reason": reason });
+    let mut response = Response::new(Full::new(Bytes::from(body.to_string())));
+    *response.status_mut() = StatusCode::SERVICE_UNAVAILABLE;
+    response.headers_mut().insert(
+        hyper::header::CONTENT_TYPE,
+        hyper::header::HeaderValue::from_static("application/json"),
+    );
+
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    use async_trait::async_trait;
+    use http_body_util::BodyExt;
+
+    use super::*;
+
+    /// Records whether its `bind` saw the shutdown signal
+    struct DrainingService {
+        drained: Arc<AtomicBool>,
+        /// Keeps running after the shutdown signal, like a service that can't drain in time
+        hang: bool,
+    }
+
+    #[async_trait]
+    impl Service for DrainingService {
+        async fn bind(
+            mut self,
+            _addr: SocketAddr,
+            shutdown: Shutdown,
+        ) -> Result<(), cyndra_service::Error> {
+            shutdown.await;
+            self.drained.store(true, Ordering::SeqCst);
+            if self.hang {
+                std::future::pending::<()>().await;
+            }
+
+            Ok(())
+        }
+    }
+
+    async fn bind_and_shut_down(hang: bool) -> (bool, bool) {
+        let drained = Arc::new(AtomicBool::new(false));
+        let service = DrainingService {
+            drained: drained.clone(),
+            hang,
+        };
+        let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel::<()>();
+        let shutdown = Shutdown::new(async move {
+            let _ = shutdown_rx.await;
+        });
+        let service_bind = service.bind(SocketAddr::from((Ipv4Addr::LOCALHOST, 0)), shutdown);
+
+        let in_time = shut_down(shutdown_tx, service_bind, Duration::from_millis(100)).await;
+
+        (in_time, drained.load(Ordering::SeqCst))
+    }
+
+    #[tokio::test]
+    async fn bind_sees_shutdown() {
+        assert_eq!(bind_and_shut_down(false).await, (true, true));
+    }
+
+    #[tokio::test]
+    async fn shutdown_gives_up_after_timeout() {
+        assert_eq!(bind_and_shut_down(true).await, (false, true));
+    }
+
+    async fn check(health_check: Option<HealthCheck>) -> (StatusCode, String) {
+        let response = health_response(health_check, Duration::from_millis(100)).await;
+        let status = response.status();
+        let body = response.into_body().collect().await.unwrap().to_bytes();
+
+        (status, String::from_utf8(body.to_vec()).unwrap())
+    }
+
+    #[tokio::test]
+    async fn healthy_without_check() {
+        assert_eq!(check(None).await.0, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn healthy_when_check_passes() {
+        let health_check = HealthCheck::new(|| async { Ok(()) });
+        assert_eq!(check(Some(health_check)).await.0, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn unhealthy_when_check_fails() {
+        let health_check = HealthCheck::new(|| async { Err("database is down".to_owned()) });
+        let (status, body) = check(Some(health_check)).await;
+
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert!(body.contains("database is down"));
+    }
+
+    #[tokio::test]
+    async fn unhealthy_and_aborted_when_check_times_out() {
+        // dropped when the check is aborted
+        let (dropped_tx, dropped_rx) = tokio::sync::oneshot::channel::<()>();
+        let dropped_tx = Arc::new(std::sync::Mutex::new(Some(dropped_tx)));
+        let health_check = HealthCheck::new(move || {
+            let guard = dropped_tx.lock().unwrap().take();
+            async move {
+                let _guard = guard;
+                std::future::pending::<Result<(), String>>().await
+            }
+        });
+        let (status, body) = check(Some(health_check)).await;
+
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert!(body.contains("did not finish"));
+        tokio::time::timeout(Duration::from_secs(1), dropped_rx)
+            .await
+            .expect("hanging health check to be aborted")
+            .unwrap_err();
+    }
+}
@@ -1,6 +1,10 @@
 use std::collections::BTreeMap;
+use std::future::Future;
 use std::net::SocketAddr;
 use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
 
 use async_trait::async_trait;
 use serde::{de::DeserializeOwned, Serialize};
@@ -11,7 +15,8 @@ pub use cyndra_common::{
         resource,
     },
     secrets::{Secret, SecretStore},
-    ContainerRequest, ContainerResponse, DatabaseInfo, DatabaseResource, DbInput,
+    ContainerReadyCheck, ContainerRequest, ContainerResponse, DatabaseInfo, DatabaseResource,
+    DbInput,
 };
 
 pub use crate::error::{CustomError, Error};
@@ -113,5 +118,92 @@ pub trait Service: Send {
     ///
     /// The passed [`SocketAddr`] receives proxied HTTP traffic from your Cyndra subdomain (or custom domain).
     /// Binding to the address is only relevant if this service is an HTTP server.
-    async fn bind(mut self, addr: SocketAddr) -> Result<(), error::Error>;
+    ///
+    /// The passed [`Shutdown`] resolves when the service should stop.
+    /// The service should then finish its in-flight work and return.
+    /// Services whose framework cannot drain connections return right away instead,
+    /// dropping any in-flight requests.
+    async fn bind(mut self, addr: SocketAddr, shutdown: Shutdown) -> Result<(), error::Error>;
+
+    /// Get a check that the runtime calls on every request to its health check endpoint
+    /// while this service is running.
+    ///
+    /// This is called once, right before [`Service::bind`].
+    /// The default has no check, meaning the service is healthy for as long as it is running.
+    fn health_check(&self) -> Option<HealthCheck> {
+        None
+    }
+
+    /// Attach a check that the runtime calls on every health check while the service is running.
+    /// Return `Err` with a reason to report the service as unhealthy.
+    fn with_health_check<F, Fut>(self, check: F) -> HealthChecked<Self>
+    where
+        Self: Sized,
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), String>> + Send + 'static,
+    {
+        HealthChecked {
+            service: self,
+            check: HealthCheck::new(check),
+        }
+    }
+}
+
+/// A future that resolves when the runtime wants the service to shut down gracefully.
+///
+/// The runtime waits for [`Service::bind`] to return for up to a drain timeout after this resolves.
+pub struct Shutdown(Pin<Box<dyn Future<Output = ()> + Send>>);
+
+impl Shutdown {
+    pub fn new(signal: impl Future<Output = ()> + Send + 'static) -> Self {
+        Self(Box::pin(signal))
+    }
+}
+
+impl Future for Shutdown {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        self.0.as_mut().poll(cx)
+    }
+}
+
+type HealthCheckFuture = Pin<Box<dyn Future<Output = Result<(), String>> + Send>>;
+
+/// A cloneable async health check for a [`Service`].
+///
+/// The check returns `Err` with a reason when the service is unhealthy.
+#[derive(Clone)]
+pub struct HealthCheck(Arc<dyn Fn() -> HealthCheckFuture + Send + Sync>);
+
+impl HealthCheck {
+    pub fn new<F, Fut>(check: F) -> Self
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<(), String>> + Send + 'static,
+    {
+        Self(Arc::new(move || Box::pin(check())))
+    }
+
+    /// Run the check once.
+    pub fn check(&self) -> impl Future<Output = Result<(), String>> + Send + 'static {
+        (self.0)()
+    }
+}
+
+/// A [`Service`] with a [`HealthCheck`] attached, see [`Service::with_health_check`].
+pub struct HealthChecked<S> {
+    service: S,
+    check: HealthCheck,
+}
+
+#[async_trait]
+impl<S: Service> Service for HealthChecked<S> {
+    async fn bind(mut self, addr: SocketAddr, shutdown: Shutdown) -> Result<(), error::Error> {
+        self.service.bind(addr, shutdown).await
+    }
+
+    fn health_check(&self) -> Option<HealthCheck> {
+        Some(self.check.clone())
+    }
 }
@@ -2,6 +2,7 @@
 use std::net::SocketAddr;
 
 pub use actix_web;
+use cyndra_runtime::Shutdown;
 
 /// A wrapper type for a closure that returns an [actix_web::web::ServiceConfig] so we can implement
 /// [cyndra_runtime::Service] for it.
@@ -13,7 +14,11 @@ impl<F> cyndra_runtime::Service for ActixWebService<F>
 where
     F: FnOnce(&mut actix_web::web::ServiceConfig) + Send + Clone + 'static,
 {
-    async fn bind(mut self, addr: SocketAddr) -> Result<(), cyndra_runtime::Error> {
+    async fn bind(
+        mut self,
+        addr: SocketAddr,
+        shutdown: Shutdown,
+    ) -> Result<(), cyndra_runtime::Error> {
         // Start a worker for each cpu, but no more than 4.
         let worker_count = num_cpus::get().min(4);
 
@@ -23,6 +28,12 @@ where
                 .bind(addr)?
                 .run();
 
+        let handle = server.handle();
+        cyndra_runtime::tokio::spawn(async move {
+            shutdown.await;
+            handle.stop(true).await;
+        });
+
         server.await.map_err(cyndra_runtime::CustomError::new)?;
 
         Ok(())
@@ -1,5 +1,5 @@
 #![doc = include_str!("../README.md")]
-use cyndra_runtime::{CustomError, Error};
+use cyndra_runtime::{CustomError, Error, Shutdown};
 use std::net::SocketAddr;
 
 #[cfg(feature = "axum")]
@@ -19,7 +19,7 @@ pub struct AxumService(pub Router);
 impl cyndra_runtime::Service for AxumService {
     /// Takes the router that is returned by the user in their [cyndra_runtime::main] function
     /// and binds to an address passed in by cyndra.
-    async fn bind(mut self, addr: SocketAddr) -> Result<(), Error> {
+    async fn bind(mut self, addr: SocketAddr, shutdown: Shutdown) -> Result<(), Error> {
         #[cfg(feature = "axum")]
         axum::serve(
             cyndra_runtime::tokio::net::TcpListener::bind(addr)
@@ -27,6 +27,7 @@ impl cyndra_runtime::Service for AxumService {
                 .map_err(CustomError::new)?,
             self.0,
         )
+        .with_graceful_shutdown(shutdown)
         .await
         .map_err(CustomError::new)?;
         #[cfg(feature = "axum-0-7")]
@@ -36,6 +37,7 @@ impl cyndra_runtime::Service for AxumService {
                 .map_err(CustomError::new)?,
             self.0,
         )
+        .with_graceful_shutdown(shutdown)
         .await
         .map_err(CustomError::new)?;
 
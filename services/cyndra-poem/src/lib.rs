@@ -10,9 +10,13 @@ impl<T> cyndra_runtime::Service for PoemService<T>
 where
     T: poem::Endpoint + Send + 'static,
 {
-    async fn bind(mut self, addr: std::net::SocketAddr) -> Result<(), cyndra_runtime::Error> {
+    async fn bind(
+        mut self,
+        addr: std::net::SocketAddr,
+        shutdown: cyndra_runtime::Shutdown,
+    ) -> Result<(), cyndra_runtime::Error> {
         poem::Server::new(poem::listener::TcpListener::bind(addr))
-            .run(self.0)
+            .run_with_graceful_shutdown(self.0, shutdown, None)
             .await
             .map_err(cyndra_runtime::CustomError::new)?;
 
@@ -6,7 +6,7 @@ use rama::{
     http::{Request, server::HttpServer, service::web::response::IntoResponse},
     tcp::server::TcpListener,
 };
-use cyndra_runtime::{CustomError, Error, tokio};
+use cyndra_runtime::{CustomError, Error, Shutdown, tokio};
 use std::{convert::Infallible, fmt, net::SocketAddr};
 
 /// A wrapper type for [`Service`] so we can implement [`cyndra_runtime::Service`] for it.
@@ -102,13 +102,17 @@ where
 {
     /// Takes the service that is returned by the user in their [cyndra_runtime::main] function
     /// and binds to an address passed in by cyndra.
-    async fn bind(self, addr: SocketAddr) -> Result<(), Error> {
-        TcpListener::build_with_state(self.state)
+    ///
+    /// This does not shut down gracefully: in-flight requests are dropped on shutdown.
+    async fn bind(self, addr: SocketAddr, shutdown: Shutdown) -> Result<(), Error> {
+        let listener = TcpListener::build_with_state(self.state)
             .bind(addr)
             .await
-            .map_err(|err| Error::BindPanic(err.to_string()))?
-            .serve(self.svc.0)
-            .await;
+            .map_err(|err| Error::BindPanic(err.to_string()))?;
+        tokio::select! {
+            _ = listener.serve(self.svc.0) => {}
+            _ = shutdown => {}
+        }
         Ok(())
     }
 }
@@ -122,13 +126,17 @@ where
 {
     /// Takes the service that is returned by the user in their [cyndra_runtime::main] function
     /// and binds to an address passed in by cyndra.
-    async fn bind(self, addr: SocketAddr) -> Result<(), Error> {
+    ///
+    /// This does not shut down gracefully: in-flight requests are dropped on shutdown.
+    async fn bind(self, addr: SocketAddr, shutdown: Shutdown) -> Result<(), Error> {
         // cyndra only supports h1 between load balancer <=> web service,
         // h2 is terminated by cyndra's load balancer
-        HttpServer::http1()
-            .listen_with_state(self.state, addr, self.svc.0)
-            .await
-            .map_err(|err| CustomError::new(OpaqueError::from_boxed(err)))?;
+        tokio::select! {
+            res = HttpServer::http1().listen_with_state(self.state, addr, self.svc.0) => {
+                res.map_err(|err| CustomError::new(OpaqueError::from_boxed(err)))?
+            }
+            _ = shutdown => {}
+        }
         Ok(())
     }
 }
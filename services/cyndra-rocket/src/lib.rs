@@ -10,8 +10,12 @@ pub struct RocketService(pub rocket::Rocket<rocket::Build>);
 impl cyndra_runtime::Service for RocketService {
     /// Takes the router that is returned by the user in their [cyndra_runtime::main] function
     /// and binds to an address passed in by cyndra.
-    async fn bind(mut self, addr: SocketAddr) -> Result<(), cyndra_runtime::Error> {
-        let shutdown = rocket::config::Shutdown {
+    async fn bind(
+        mut self,
+        addr: SocketAddr,
+        shutdown: cyndra_runtime::Shutdown,
+    ) -> Result<(), cyndra_runtime::Error> {
+        let shutdown_config = rocket::config::Shutdown {
             ctrlc: false,
             ..rocket::config::Shutdown::default()
         };
@@ -23,11 +27,22 @@ impl cyndra_runtime::Service for RocketService {
             .merge((rocket::Config::ADDRESS, addr.ip()))
             .merge((rocket::Config::PORT, addr.port()))
             .merge((rocket::Config::LOG_LEVEL, rocket::config::LogLevel::Off))
-            .merge((rocket::Config::SHUTDOWN, shutdown));
+            .merge((rocket::Config::SHUTDOWN, shutdown_config));
 
-        let _rocket = self
+        let rocket = self
             .0
             .configure(config)
+            .ignite()
+            .await
+            .map_err(cyndra_runtime::CustomError::new)?;
+
+        let handle = rocket.shutdown();
+        cyndra_runtime::tokio::spawn(async move {
+            shutdown.await;
+            handle.notify();
+        });
+
+        let _rocket = rocket
             .launch()
             .await
             .map_err(cyndra_runtime::CustomError::new)?;
@@ -1,6 +1,6 @@
 #![doc = include_str!("../README.md")]
 use salvo::Listener;
-use cyndra_runtime::Error;
+use cyndra_runtime::{Error, Shutdown};
 use std::net::SocketAddr;
 
 pub use salvo;
@@ -12,10 +12,17 @@ pub struct SalvoService(pub salvo::Router);
 impl cyndra_runtime::Service for SalvoService {
     /// Takes the router that is returned by the user in their [cyndra_runtime::main] function
     /// and binds to an address passed in by cyndra.
-    async fn bind(mut self, addr: SocketAddr) -> Result<(), Error> {
+    async fn bind(mut self, addr: SocketAddr, shutdown: Shutdown) -> Result<(), Error> {
         let listener = salvo::conn::TcpListener::new(addr).bind().await;
 
-        salvo::Server::new(listener).serve(self.0).await;
+        let server = salvo::Server::new(listener);
+        let handle = server.handle();
+        cyndra_runtime::tokio::spawn(async move {
+            shutdown.await;
+            handle.stop_graceful(None);
+        });
+
+        server.serve(self.0).await;
 
         Ok(())
     }
@@ -1,5 +1,5 @@
 #![doc = include_str!("../README.md")]
-use cyndra_runtime::{CustomError, Error};
+use cyndra_runtime::{CustomError, Error, Shutdown};
 use std::net::SocketAddr;
 
 #[cfg(feature = "serenity")]
@@ -19,7 +19,16 @@ pub struct SerenityService(pub Client);
 impl cyndra_runtime::Service for SerenityService {
     /// Takes the client that is returned by the user in their [cyndra_runtime::main] function
     /// and starts it.
-    async fn bind(mut self, _addr: SocketAddr) -> Result<(), Error> {
+    async fn bind(mut self, _addr: SocketAddr, shutdown: Shutdown) -> Result<(), Error> {
+        let shard_manager = self.0.shard_manager.clone();
+        cyndra_runtime::tokio::spawn(async move {
+            shutdown.await;
+            #[cfg(feature = "serenity")]
+            shard_manager.shutdown_all().await;
+            #[cfg(feature = "serenity-0-11")]
+            shard_manager.lock().await.shutdown_all().await;
+        });
+
         self.0.start_autosharded().await.map_err(CustomError::new)?;
 
         Ok(())
@@ -1,5 +1,5 @@
 #![doc = include_str!("../README.md")]
-use cyndra_runtime::Error;
+use cyndra_runtime::{Error, Shutdown};
 use std::net::SocketAddr;
 
 pub use thruster;
@@ -14,8 +14,16 @@ where
 {
     /// Takes the server that is returned by the user in their [cyndra_runtime::main] function
     /// and binds to an address passed in by cyndra.
-    async fn bind(mut self, addr: SocketAddr) -> Result<(), Error> {
-        self.0.build(&addr.ip().to_string(), addr.port()).await;
+    ///
+    /// This does not shut down gracefully: in-flight requests are dropped on shutdown.
+    async fn bind(mut self, addr: SocketAddr, shutdown: Shutdown) -> Result<(), Error> {
+        let host = addr.ip().to_string();
+
+        // thruster has no graceful shutdown, so stop listening right away
+        cyndra_runtime::tokio::select! {
+            _ = self.0.build(&host, addr.port()) => {}
+            _ = shutdown => {}
+        }
 
         Ok(())
     }
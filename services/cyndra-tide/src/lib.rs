@@ -1,5 +1,5 @@
 #![doc = include_str!("../README.md")]
-use cyndra_runtime::{CustomError, Error};
+use cyndra_runtime::{CustomError, Error, Shutdown};
 use std::net::SocketAddr;
 
 pub use tide;
@@ -14,8 +14,14 @@ where
 {
     /// Takes the router that is returned by the user in their [cyndra_runtime::main] function
     /// and binds to an address passed in by cyndra.
-    async fn bind(mut self, addr: SocketAddr) -> Result<(), Error> {
-        self.0.listen(addr).await.map_err(CustomError::new)?;
+    ///
+    /// This does not shut down gracefully: in-flight requests are dropped on shutdown.
+    async fn bind(mut self, addr: SocketAddr, shutdown: Shutdown) -> Result<(), Error> {
+        // tide has no graceful shutdown, so stop listening right away
+        cyndra_runtime::tokio::select! {
+            res = self.0.listen(addr) => res.map_err(CustomError::new)?,
+            _ = shutdown => {}
+        }
 
         Ok(())
     }
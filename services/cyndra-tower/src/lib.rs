@@ -1,5 +1,5 @@
 #![doc = include_str!("../README.md")]
-use cyndra_runtime::{CustomError, Error};
+use cyndra_runtime::{CustomError, Error, Shutdown};
 use std::net::SocketAddr;
 
 pub use tower;
@@ -20,10 +20,11 @@ where
 {
     /// Takes the service that is returned by the user in their [cyndra_runtime::main] function
     /// and binds to an address passed in by cyndra.
-    async fn bind(mut self, addr: SocketAddr) -> Result<(), Error> {
+    async fn bind(mut self, addr: SocketAddr, shutdown: Shutdown) -> Result<(), Error> {
         let shared = tower::make::Shared::new(self.0);
         hyper::Server::bind(&addr)
             .serve(shared)
+            .with_graceful_shutdown(shutdown)
             .await
             .map_err(CustomError::new)?;
 
@@ -1,5 +1,5 @@
 #![doc = include_str!("../README.md")]
-use cyndra_runtime::Error;
+use cyndra_runtime::{Error, Shutdown};
 use std::net::SocketAddr;
 use std::ops::Deref;
 
@@ -16,8 +16,9 @@ where
 {
     /// Takes the router that is returned by the user in their [cyndra_runtime::main] function
     /// and binds to an address passed in by cyndra.
-    async fn bind(mut self, addr: SocketAddr) -> Result<(), Error> {
-        warp::serve((*self).clone()).run(addr).await;
+    async fn bind(mut self, addr: SocketAddr, shutdown: Shutdown) -> Result<(), Error> {
+        let (_, server) = warp::serve((*self).clone()).bind_with_graceful_shutdown(addr, shutdown);
+        server.await;
         Ok(())
     }
 }